@@ -1,15 +1,94 @@
 use crate::vector3::Vector3;
+use crate::wind::WindField;
+use crate::psychrometrics::{self, SaturationCurve};
+use crate::projectiles::Projectile;
+use crate::standard_atmosphere::{self, StandardAtmosphere};
+use crate::root_finding;
 use crate::constants;
 use crate::si_units as units;
 use std::f64::consts::E;
+use std::sync::Arc;
 use log::warn;
 
-#[derive(Copy, Clone)]
+// Elevations searched for a density altitude (m)
+const MIN_ELEVATION: f64 = -10_000. * units::M;
+const MAX_ELEVATION: f64 = 50_000. * units::M;
+const ELEVATION_TOL: f64 = 1e-6 * units::M;
+const MAX_ITER: usize = 100;
+
+// Air properties as functions of altitude above sea level (m)
+// Trajectories query them at reference_elevation + z along the flight
+pub trait AtmosphereModel: Send + Sync {
+    // Altitude of z = 0 in trajectory coordinates
+    fn reference_elevation(&self) -> f64;
+
+    fn temperature_at(&self, altitude: f64) -> f64;
+
+    fn pressure_at(&self, altitude: f64) -> f64;
+
+    fn density_at(&self, altitude: f64) -> f64;
+
+    fn dynamic_viscosity_at(&self, altitude: f64) -> f64 {
+        // Sutherland's law for air, gives viscosity in Pa*s
+        // https://en.wikipedia.org/wiki/Viscosity#Effect_of_temperature_on_the_viscosity_of_a_gas
+        let mu_ref = 1.716e-5 * units::PA * units::S;
+        let temp_ref = 273.15 * units::K;
+        let sutherland_temp = 110.4 * units::K;
+        let temperature = self.temperature_at(altitude);
+        mu_ref * (temperature / temp_ref).powf(1.5)
+            * (temp_ref + sutherland_temp) / (temperature + sutherland_temp)
+    }
+
+    fn kinematic_viscosity_at(&self, altitude: f64) -> f64 {
+        self.dynamic_viscosity_at(altitude) / self.density_at(altitude)
+    }
+
+    fn thermal_conductivity_at(&self, altitude: f64) -> f64 {
+        // Dry air, as given in the U.S. Standard Atmosphere 1976, W/(m*K)
+        let temperature = self.temperature_at(altitude);
+        2.646_38e-3 * temperature.powf(1.5)
+            / (temperature + 245.4 * 10f64.powf(-12. / temperature))
+    }
+
+    fn speed_of_sound_at(&self, altitude: f64) -> f64 {
+        (constants::AIR_ADIABATIC_INDEX * constants::R_SPEC_DRY_AIR
+            * self.temperature_at(altitude)).sqrt()
+    }
+
+    // Wind velocity at a point in trajectory coordinates
+    fn wind(&self, position: Vector3, time: f64) -> Vector3;
+
+    // Reynolds number of the projectile, based on its diameter and
+    // airspeed, with the air where it is now
+    fn reynolds_number(&self, projectile: &Projectile) -> f64 {
+        let altitude = self.reference_elevation() + projectile.position.z;
+        let airspeed = (projectile.get_velocity()
+            - self.wind(projectile.position, 0.)).mag();
+        self.density_at(altitude) * airspeed * 2. * projectile.radius
+            / self.dynamic_viscosity_at(altitude)
+    }
+
+    fn mach_number(&self, projectile: &Projectile) -> f64 {
+        let altitude = self.reference_elevation() + projectile.position.z;
+        let airspeed = (projectile.get_velocity()
+            - self.wind(projectile.position, 0.)).mag();
+        airspeed / self.speed_of_sound_at(altitude)
+    }
+}
+
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Clone)]
 pub struct Atmosphere {
     pub temperature: f64,
+    // Relative humidity, 0 to 1
     pub humidity: f64,
     pub elevation: f64,
     pub wind: Vector3,
+    // Position and time dependent wind, used instead of wind when set
+    pub wind_field: Option<Arc<dyn WindField>>,
+    pub saturation_curve: SaturationCurve,
+    // Pressure the isothermal profile gives at sea level
+    pub sea_level_pressure: f64,
     _private: (),
 }
 
@@ -25,52 +104,259 @@ impl Atmosphere {
             humidity = 0.;
         }
         Atmosphere{ temperature, humidity,
-            elevation, wind, _private: () }
+            elevation, wind, wind_field: None,
+            saturation_curve: SaturationCurve::default(),
+            sea_level_pressure: constants::PRESSURE_SEA_LEVEL, _private: () }
+    }
+
+    // Pressure measured at the given elevation
+    pub fn from_station_pressure(temperature: f64, humidity: f64,
+        station_pressure: f64, elevation: f64, wind: Vector3) -> Atmosphere {
+        let mut atm = Atmosphere::new(temperature, humidity, elevation, wind);
+        atm.sea_level_pressure *= station_pressure / atm.pressure();
+        atm
+    }
+
+    // Pressure from an altimeter setting (QNH) reported for a station
+    // at the given elevation, e.g. 29.92 * units::INHG or 1013. * units::HPA
+    pub fn from_altimeter_setting(temperature: f64, humidity: f64,
+        altimeter_setting: f64, elevation: f64, wind: Vector3) -> Atmosphere {
+        Atmosphere::from_station_pressure(temperature, humidity,
+            standard_atmosphere::station_pressure(altimeter_setting, elevation),
+            elevation, wind)
+    }
+
+    // Elevation that gives the air the density of the standard atmosphere at
+    // geopotential density_altitude, None if no elevation within the search
+    // range does
+    pub fn from_density_altitude(density_altitude: f64, temperature: f64,
+        humidity: f64, wind: Vector3) -> Option<Atmosphere> {
+        let standard = StandardAtmosphere::new(0., wind);
+        let density = standard.density_at(
+            standard_atmosphere::geometric_altitude(density_altitude));
+        let atm = Atmosphere::new(temperature, humidity, 0., wind);
+        let elevation = root_finding::brent(
+            |elevation| atm.density_at(elevation) - density,
+            MIN_ELEVATION, MAX_ELEVATION, ELEVATION_TOL, MAX_ITER)?;
+        Some(Atmosphere::new(temperature, humidity, elevation, wind))
+    }
+
+    // Humidity given by the dew point temperature, converted with the
+    // given saturation curve, which the atmosphere keeps
+    pub fn from_dew_point(temperature: f64, dew_point: f64,
+        elevation: f64, wind: Vector3, curve: SaturationCurve) -> Atmosphere {
+        let humidity = psychrometrics::relative_humidity(
+            curve.pressure(dew_point), temperature, &curve);
+        let mut atm = Atmosphere::new(temperature, humidity, elevation, wind);
+        atm.saturation_curve = curve;
+        atm
+    }
+
+    // Humidity given by the wet bulb temperature, converted with the
+    // given saturation curve, which the atmosphere keeps
+    pub fn from_wet_bulb(temperature: f64, wet_bulb: f64,
+        elevation: f64, wind: Vector3, curve: SaturationCurve) -> Atmosphere {
+        let mut atm = Atmosphere::new(temperature, 0., elevation, wind);
+        atm.saturation_curve = curve;
+        let vapor_pressure = psychrometrics::vapor_pressure_from_wet_bulb(
+            temperature, wet_bulb, atm.pressure(), &atm.saturation_curve);
+        atm.humidity = psychrometrics::relative_humidity(
+            vapor_pressure, temperature, &atm.saturation_curve).clamp(0., 1.);
+        atm
     }
 
 
     pub fn pressure(&self) -> f64 {
-        let exp_num = -constants::G * constants::AIR_MOL_MASS * self.elevation;
-        let exp_denom = constants::R * self.temperature;
-        E.powf(exp_num / exp_denom) * constants::PRESSURE_SEA_LEVEL
+        self.pressure_at(self.elevation)
+    }
+
+    // Geopotential altitude in the standard atmosphere with the same pressure
+    pub fn pressure_altitude(&self) -> Option<f64> {
+        standard_atmosphere::pressure_altitude(self.pressure())
+    }
+
+    // Geopotential altitude in the standard atmosphere with the same density
+    pub fn density_altitude(&self) -> Option<f64> {
+        standard_atmosphere::density_altitude(self.air_density())
     }
 
     pub fn saturation_pressure(&self) -> f64 {
-        // Tetens Equation, gives pressure in kPa
-        // https://en.wikipedia.org/wiki/Tetens_equation
-        // Magic coefficients
-        let coef1 = 0.61078;
-        let temp_in_c = self.temperature + units::KTOC;
-        if temp_in_c < 0. {
-            let coef2 = 17.27;
-            let exp_num = coef2 * self.temperature;
-            let low_temp_shift = 265.5;
-            let exp_denom = temp_in_c + low_temp_shift;
-            coef1 * E.powf(exp_num / exp_denom) * units::PA / units::KPA
-        } else {
-            let coef2 = 21.875;
-            let exp_num = coef2 * self.temperature;
-            let high_temp_shift = 237.3;
-            let exp_denom = temp_in_c + high_temp_shift;
-            coef1 * E.powf(exp_num / exp_denom) * units::PA / units::KPA
-        }
+        self.saturation_curve.pressure(self.temperature)
     }
 
     pub fn vapor_pressure(&self) -> f64 {
         self.humidity * self.saturation_pressure()
     }
+
+    pub fn dew_point(&self) -> Option<f64> {
+        psychrometrics::dew_point(self.vapor_pressure(), &self.saturation_curve)
+    }
+
+    pub fn wet_bulb(&self) -> Option<f64> {
+        psychrometrics::wet_bulb(self.temperature, self.vapor_pressure(),
+            self.pressure(), &self.saturation_curve)
+    }
+
     pub fn dry_air_density(&self) -> f64 {
         self.pressure() * constants::AIR_MOLEC_MASS /
             (constants::KB * self.temperature)
     }
 
     pub fn air_density(&self) -> f64 {
+        self.density_at(self.elevation)
+    }
+
+    pub fn dynamic_viscosity(&self) -> f64 {
+        self.dynamic_viscosity_at(self.elevation)
+    }
+
+    pub fn kinematic_viscosity(&self) -> f64 {
+        self.kinematic_viscosity_at(self.elevation)
+    }
+
+    pub fn thermal_conductivity(&self) -> f64 {
+        self.thermal_conductivity_at(self.elevation)
+    }
+
+    // Includes the effect of humidity
+    pub fn speed_of_sound(&self) -> f64 {
+        self.speed_of_sound_at(self.elevation)
+    }
+}
+
+// Isothermal, with constant humidity
+impl AtmosphereModel for Atmosphere {
+    fn reference_elevation(&self) -> f64 {
+        self.elevation
+    }
+
+    fn temperature_at(&self, _altitude: f64) -> f64 {
+        self.temperature
+    }
+
+    fn pressure_at(&self, altitude: f64) -> f64 {
+        let exp_num = -constants::G * constants::AIR_MOL_MASS * altitude;
+        let exp_denom = constants::R * self.temperature;
+        E.powf(exp_num / exp_denom) * self.sea_level_pressure
+    }
+
+    fn density_at(&self, altitude: f64) -> f64 {
         let vapor_pressure = self.vapor_pressure();
-        let partial_air_pressure = self.pressure() - vapor_pressure;
+        let partial_air_pressure = self.pressure_at(altitude) - vapor_pressure;
         let dry_air =  partial_air_pressure * constants::AIR_MOL_MASS;
         let vapor = vapor_pressure * constants::WATER_VAPOR_MOL_MASS;
         let num = dry_air + vapor;
         let denom = constants::R * self.temperature;
         num / denom
     }
+
+    fn speed_of_sound_at(&self, altitude: f64) -> f64 {
+        psychrometrics::speed_of_sound(self.temperature,
+            self.vapor_pressure(), self.pressure_at(altitude))
+    }
+
+    fn wind(&self, position: Vector3, time: f64) -> Vector3 {
+        match &self.wind_field {
+            Some(field) => field.velocity(position, time),
+            None => self.wind,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOT: f64 = 308.15;
+
+    fn calm() -> Vector3 {
+        Vector3::new(0., 0., 0.)
+    }
+
+    #[test]
+    fn station_pressure_and_altimeter_setting() {
+        let atm = Atmosphere::from_station_pressure(HOT, 0.3,
+            85_000. * units::PA, 1_500., calm());
+        assert!((atm.pressure() - 85_000.).abs() < 1e-6);
+        assert!((atm.pressure_at(1_500.) - 85_000.).abs() < 1e-6);
+        assert!(atm.pressure_at(2_000.) < atm.pressure());
+
+        // 29.92 inHg is the standard sea level pressure to 4 digits
+        let atm = Atmosphere::from_altimeter_setting(HOT, 0.3,
+            29.92 * units::INHG, 0., calm());
+        assert!((atm.pressure() - 101_325.).abs() < 10.);
+        assert!(atm.pressure_altitude().unwrap().abs() < 1.);
+
+        // A standard altimeter setting gives the standard station pressure
+        let atm = Atmosphere::from_altimeter_setting(HOT, 0.3,
+            1013.25 * units::HPA, 1_500., calm());
+        let standard = StandardAtmosphere::new(0., calm());
+        assert!((atm.pressure() / standard.pressure_at(1_500.) - 1.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn density_altitude_round_trips() {
+        for density_altitude in [-500., 0., 1_500., 3_000.] {
+            let atm = Atmosphere::from_density_altitude(density_altitude,
+                HOT, 0.5, calm()).unwrap();
+            assert!((atm.density_altitude().unwrap() - density_altitude).abs() < 1e-3);
+        }
+        // Thinner than the isothermal air gets within the search range
+        assert!(Atmosphere::from_density_altitude(80_000., HOT, 0., calm()).is_none());
+
+        // Hot humid air at sea level is thinner than standard,
+        // cold dry air denser
+        let hot = Atmosphere::new(HOT, 0.8, 0., calm());
+        assert!(hot.density_altitude().unwrap() > 500.);
+        assert!(hot.pressure_altitude().unwrap().abs() < 1.);
+        let cold = Atmosphere::new(263.15, 0., 0., calm());
+        assert!(cold.density_altitude().unwrap() < -500.);
+    }
+
+    // Sea level standard day with only the required methods, so the
+    // provided ones are the trait defaults
+    struct SeaLevel;
+
+    impl AtmosphereModel for SeaLevel {
+        fn reference_elevation(&self) -> f64 {
+            0.
+        }
+
+        fn temperature_at(&self, _altitude: f64) -> f64 {
+            288.15
+        }
+
+        fn pressure_at(&self, _altitude: f64) -> f64 {
+            101_325.
+        }
+
+        fn density_at(&self, _altitude: f64) -> f64 {
+            1.225
+        }
+
+        fn wind(&self, _position: Vector3, _time: f64) -> Vector3 {
+            Vector3::new(-5., 0., 0.)
+        }
+    }
+
+    #[test]
+    fn default_air_properties_at_sea_level() {
+        // Standard atmosphere table values
+        assert!((SeaLevel.dynamic_viscosity_at(0.) - 1.789e-5).abs() < 1e-8);
+        assert!((SeaLevel.kinematic_viscosity_at(0.) - 1.461e-5).abs() < 1e-8);
+        assert!((SeaLevel.thermal_conductivity_at(0.) - 2.5326e-2).abs() < 1e-6);
+        assert!((SeaLevel.speed_of_sound_at(0.) - 340.29).abs() < 0.01);
+    }
+
+    // Golf ball driven at 70 m/s into a 5 m/s headwind
+    #[test]
+    fn reynolds_and_mach_numbers_use_the_airspeed() {
+        let mut ball = Projectile::new();
+        ball.radius = 42.67 * units::MM / 2.;
+        ball.set_speed_theta_phi(70., 0., 0.);
+        let reynolds = 1.225 * 75. * 42.67e-3 / 1.7894e-5;
+        assert!((SeaLevel.reynolds_number(&ball) / reynolds - 1.).abs() < 1e-4);
+        assert!((SeaLevel.mach_number(&ball) - 75. / 340.29).abs() < 1e-4);
+    }
 }
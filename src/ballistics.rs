@@ -0,0 +1,398 @@
+// Point mass external ballistics for bullets described by a
+// ballistic coefficient and a standard drag function
+//
+// A standard drag function is the Cd(Mach) of a reference projectile.
+// A bullet with ballistic coefficient BC = m / (i d^2) has i times the
+// reference drag coefficient, i being its form factor, so its drag
+// deceleration is rho * v^2 * Cd_ref(M) * pi / (8 * BC)
+// Ballistic coefficients are usually quoted in lb/in^2, e.g.
+// 0.243 * units::LB / (units::IN * units::IN)
+// https://en.wikipedia.org/wiki/External_ballistics
+
+use std::sync::Arc;
+
+use crate::vector3::Vector3;
+use crate::si_units as units;
+use crate::atmosphere::AtmosphereModel;
+use crate::integrators::Integrator;
+use crate::aerodynamics::{interpolate, AeroModel};
+use crate::events::{Direction, Event};
+use crate::projectiles::Projectile;
+use crate::root_finding;
+
+// Long enough for any supersonic or subsonic rifle shot (s)
+const MAX_FLIGHT_TIME: f64 = 60.;
+// Elevations searched for the zero
+const ZERO_MIN_ANGLE: f64 = -10. * units::DEGREES;
+const ZERO_MAX_ANGLE: f64 = 30. * units::DEGREES;
+const ANGLE_TOL: f64 = 1e-10;
+const MAX_ITER: usize = 100;
+
+// Standard drag functions as (Mach, Cd) tables
+// GL (exposed lead nose) is not included yet, for lack of a table from
+// a source that can be cited, BallisticDrag::new takes any table
+#[derive(Copy, Clone)]
+pub enum DragFunction {
+    // Flat base, short ogive nose (Ingalls / Aberdeen G1)
+    G1,
+    // Aberdeen J projectile
+    G2,
+    // Short boat tail, long ogive nose
+    G5,
+    // Flat base, secant ogive nose
+    G6,
+    // Long boat tail, tangent ogive nose (very low drag rifle bullets)
+    G7,
+    // Flat base, long secant ogive nose
+    G8,
+}
+
+// Cd of a standard drag function scaled by the form factor
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Clone)]
+pub struct BallisticDrag {
+    pub table: Vec<(f64, f64)>,
+    pub form_factor: f64,
+    _private: (),
+}
+
+// One line of a range table, drop is how far the bullet is below the
+// line of sight (negative above it) and windage is to the left of the
+// firing line
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct RangeRow {
+    pub range: f64,
+    pub drop: f64,
+    pub windage: f64,
+    pub time: f64,
+    pub velocity: f64,
+    pub energy: f64,
+    _private: (),
+}
+
+impl DragFunction {
+    pub fn table(&self) -> Vec<(f64, f64)> {
+        match self {
+            DragFunction::G1 => G1.to_vec(),
+            DragFunction::G2 => G2.to_vec(),
+            DragFunction::G5 => G5.to_vec(),
+            DragFunction::G6 => G6.to_vec(),
+            DragFunction::G7 => G7.to_vec(),
+            DragFunction::G8 => G8.to_vec(),
+        }
+    }
+}
+
+impl BallisticDrag {
+    // Any (Mach, Cd) table sorted by Mach, e.g. one measured by Doppler
+    // radar or a drag function not listed in DragFunction
+    pub fn new(table: Vec<(f64, f64)>, form_factor: f64) -> BallisticDrag {
+        BallisticDrag{ table, form_factor, _private: () }
+    }
+
+    // Form factor of a bullet with the given ballistic coefficient (kg/m^2)
+    // relative to the table's reference projectile
+    pub fn from_ballistic_coefficient(table: Vec<(f64, f64)>, mass: f64,
+        diameter: f64, ballistic_coefficient: f64) -> BallisticDrag {
+        BallisticDrag::new(table,
+            mass / (ballistic_coefficient * diameter.powi(2)))
+    }
+}
+
+impl AeroModel for BallisticDrag {
+    // Low speed value, trajectories use drag_coefficient_mach
+    fn drag_coefficient(&self, _reynolds: f64, _spin_ratio: f64) -> f64 {
+        self.form_factor * interpolate(&self.table, 0.)
+    }
+
+    fn lift_coefficient(&self, _reynolds: f64, _spin_ratio: f64) -> f64 {
+        0.
+    }
+
+    fn drag_coefficient_mach(&self, _reynolds: f64, _spin_ratio: f64,
+        mach: f64) -> f64 {
+        self.form_factor * interpolate(&self.table, mach)
+    }
+}
+
+impl RangeRow {
+    pub fn new(range: f64, drop: f64, windage: f64, time: f64,
+        velocity: f64, energy: f64) -> RangeRow {
+        RangeRow{ range, drop, windage, time, velocity, energy, _private: () }
+    }
+}
+
+impl Projectile {
+    // Point mass bullet with drag from a (Mach, Cd) table,
+    // see DragFunction::table
+    pub fn bullet(mass: f64, diameter: f64, ballistic_coefficient: f64,
+        table: Vec<(f64, f64)>) -> Projectile {
+        let mut bullet = Projectile::new();
+        bullet.mass = mass;
+        bullet.radius = diameter / 2.;
+        bullet.aero_model = Some(Arc::new(BallisticDrag::from_ballistic_coefficient(
+            table, mass, diameter, ballistic_coefficient)));
+        bullet
+    }
+
+    // Elevation that puts the bullet on a level line of sight
+    // sight_height above the bore at zero_range, at the current speed
+    // and azimuth, None if the zero cannot be reached
+    pub fn zero_elevation(
+        &self, atm: &dyn AtmosphereModel, integrator: &dyn Integrator,
+        t_step: f64, sight_height: f64, zero_range: f64) -> Option<f64> {
+
+        let phi = self.get_phi();
+        let target = self.position
+            + Vector3::new(zero_range * phi.cos(), zero_range * phi.sin(),
+                sight_height);
+        let height_miss = |theta: f64| {
+            self.miss(atm, integrator, t_step,
+                (self.get_speed(), theta, phi), target)
+                .map_or(-f64::MAX, |miss| miss.height)
+        };
+        root_finding::brent(height_miss, ZERO_MIN_ANGLE, ZERO_MAX_ANGLE,
+            ANGLE_TOL, MAX_ITER)
+    }
+
+    // Drop, windage, time, velocity and energy every range_step along
+    // the firing azimuth out to max_range, fired as currently set up
+    // Rows stop early if the bullet falls short
+    pub fn range_table(
+        &self, atm: &dyn AtmosphereModel, integrator: &dyn Integrator,
+        t_step: f64, sight_height: f64, range_step: f64, max_range: f64)
+        -> Vec<RangeRow> {
+
+        let start = self.position;
+        let phi = self.get_phi();
+        let bearing = Vector3::new(phi.cos(), phi.sin(), 0.);
+        let n_rows = (max_range / range_step).floor() as usize;
+        let events: Vec<Event> = (1..=n_rows).map(|k| {
+            let range = k as f64 * range_step;
+            Event::new(move |_t, state| {
+                (state.position - start).dot(bearing) - range
+            }, Direction::Rising, k == n_rows)
+        }).collect();
+
+        // No ground, the line of sight sets the drop
+        let mut shot = self.clone();
+        let result = shot.trajectory_with_events(atm, integrator, t_step,
+            f64::NEG_INFINITY, MAX_FLIGHT_TIME, &events);
+
+        result.events.iter().map(|(k, hit)| {
+            let delta = hit.position - start;
+            let speed = hit.velocity.mag();
+            RangeRow::new((k + 1) as f64 * range_step,
+                sight_height - delta.z,
+                Vector3::cross_prod(&bearing, &delta).z,
+                hit.time, speed, self.mass * speed.powi(2) / 2.)
+        }).collect()
+    }
+}
+
+
+/***********
+************
+Drag Tables
+************
+***********/
+
+// (Mach, Cd) from the standard tables as tabulated by JBM Ballistics
+// https://www.jbmballistics.com/ballistics/downloads/downloads.shtml
+// Some Cd values look like math constants to clippy
+
+const G1: &[(f64, f64)] = &[
+    (0.00, 0.2629), (0.05, 0.2558), (0.10, 0.2487), (0.15, 0.2413),
+    (0.20, 0.2344), (0.25, 0.2278), (0.30, 0.2214), (0.35, 0.2155),
+    (0.40, 0.2104), (0.45, 0.2061), (0.50, 0.2032), (0.55, 0.2020),
+    (0.60, 0.2034), (0.65, 0.2080), (0.70, 0.2165), (0.725, 0.2230),
+    (0.75, 0.2313), (0.775, 0.2417), (0.80, 0.2546), (0.825, 0.2706),
+    (0.85, 0.2901), (0.875, 0.3136), (0.90, 0.3415), (0.925, 0.3734),
+    (0.95, 0.4084), (0.975, 0.4448), (1.00, 0.4805), (1.025, 0.5136),
+    (1.05, 0.5427), (1.075, 0.5677), (1.10, 0.5883), (1.125, 0.6053),
+    (1.15, 0.6191), (1.20, 0.6393), (1.25, 0.6518), (1.30, 0.6589),
+    (1.35, 0.6621), (1.40, 0.6625), (1.45, 0.6607), (1.50, 0.6573),
+    (1.55, 0.6528), (1.60, 0.6474), (1.65, 0.6413), (1.70, 0.6347),
+    (1.75, 0.6280), (1.80, 0.6210), (1.85, 0.6141), (1.90, 0.6072),
+    (1.95, 0.6003), (2.00, 0.5934), (2.05, 0.5867), (2.10, 0.5804),
+    (2.15, 0.5743), (2.20, 0.5685), (2.25, 0.5630), (2.30, 0.5577),
+    (2.35, 0.5527), (2.40, 0.5481), (2.45, 0.5438), (2.50, 0.5397),
+    (2.60, 0.5325), (2.70, 0.5264), (2.80, 0.5211), (2.90, 0.5168),
+    (3.00, 0.5133), (3.10, 0.5105), (3.20, 0.5084), (3.30, 0.5067),
+    (3.40, 0.5054), (3.50, 0.5040), (3.60, 0.5030), (3.70, 0.5022),
+    (3.80, 0.5016), (3.90, 0.5010), (4.00, 0.5006), (4.20, 0.4998),
+    (4.40, 0.4995), (4.60, 0.4992), (4.80, 0.4990), (5.00, 0.4988),
+];
+
+const G2: &[(f64, f64)] = &[
+    (0.00, 0.2303), (0.05, 0.2298), (0.10, 0.2287), (0.15, 0.2271),
+    (0.20, 0.2251), (0.25, 0.2227), (0.30, 0.2196), (0.35, 0.2156),
+    (0.40, 0.2107), (0.45, 0.2048), (0.50, 0.1980), (0.55, 0.1905),
+    (0.60, 0.1828), (0.65, 0.1758), (0.70, 0.1702), (0.75, 0.1669),
+    (0.775, 0.1664), (0.80, 0.1667), (0.825, 0.1682), (0.85, 0.1711),
+    (0.875, 0.1761), (0.90, 0.1831), (0.925, 0.2004), (0.95, 0.2589),
+    (0.975, 0.3492), (1.00, 0.3983), (1.025, 0.4075), (1.05, 0.4103),
+    (1.075, 0.4114), (1.10, 0.4106), (1.125, 0.4089), (1.15, 0.4068),
+    (1.175, 0.4046), (1.20, 0.4021), (1.25, 0.3966), (1.30, 0.3904),
+    (1.35, 0.3835), (1.40, 0.3759), (1.45, 0.3678), (1.50, 0.3594),
+    (1.55, 0.3512), (1.60, 0.3432), (1.65, 0.3356), (1.70, 0.3282),
+    (1.75, 0.3213), (1.80, 0.3149), (1.85, 0.3089), (1.90, 0.3033),
+    (1.95, 0.2982), (2.00, 0.2933), (2.05, 0.2889), (2.10, 0.2846),
+    (2.15, 0.2806), (2.20, 0.2768), (2.25, 0.2731), (2.30, 0.2696),
+    (2.35, 0.2663), (2.40, 0.2632), (2.45, 0.2602), (2.50, 0.2572),
+    (2.55, 0.2543), (2.60, 0.2515), (2.65, 0.2487), (2.70, 0.2460),
+    (2.75, 0.2433), (2.80, 0.2408), (2.85, 0.2382), (2.90, 0.2357),
+    (2.95, 0.2333), (3.00, 0.2309), (3.10, 0.2262), (3.20, 0.2217),
+    (3.30, 0.2173), (3.40, 0.2132), (3.50, 0.2091), (3.60, 0.2052),
+    (3.70, 0.2014), (3.80, 0.1978), (3.90, 0.1944), (4.00, 0.1912),
+    (4.20, 0.1851), (4.40, 0.1794), (4.60, 0.1741), (4.80, 0.1693),
+    (5.00, 0.1648),
+];
+
+#[allow(clippy::approx_constant)]
+const G5: &[(f64, f64)] = &[
+    (0.00, 0.1710), (0.05, 0.1719), (0.10, 0.1727), (0.15, 0.1732),
+    (0.20, 0.1734), (0.25, 0.1730), (0.30, 0.1718), (0.35, 0.1696),
+    (0.40, 0.1668), (0.45, 0.1637), (0.50, 0.1603), (0.55, 0.1566),
+    (0.60, 0.1529), (0.65, 0.1497), (0.70, 0.1473), (0.75, 0.1463),
+    (0.80, 0.1489), (0.85, 0.1583), (0.875, 0.1672), (0.90, 0.1815),
+    (0.925, 0.2051), (0.95, 0.2413), (0.975, 0.2884), (1.00, 0.3379),
+    (1.025, 0.3785), (1.05, 0.4032), (1.075, 0.4147), (1.10, 0.4201),
+    (1.15, 0.4278), (1.20, 0.4338), (1.25, 0.4373), (1.30, 0.4392),
+    (1.35, 0.4403), (1.40, 0.4406), (1.45, 0.4401), (1.50, 0.4386),
+    (1.55, 0.4362), (1.60, 0.4328), (1.65, 0.4286), (1.70, 0.4237),
+    (1.75, 0.4182), (1.80, 0.4121), (1.85, 0.4057), (1.90, 0.3991),
+    (1.95, 0.3926), (2.00, 0.3861), (2.05, 0.3800), (2.10, 0.3741),
+    (2.15, 0.3684), (2.20, 0.3630), (2.25, 0.3578), (2.30, 0.3529),
+    (2.35, 0.3481), (2.40, 0.3435), (2.45, 0.3391), (2.50, 0.3349),
+    (2.60, 0.3269), (2.70, 0.3194), (2.80, 0.3125), (2.90, 0.3060),
+    (3.00, 0.2999), (3.10, 0.2942), (3.20, 0.2889), (3.30, 0.2838),
+    (3.40, 0.2790), (3.50, 0.2745), (3.60, 0.2703), (3.70, 0.2662),
+    (3.80, 0.2624), (3.90, 0.2588), (4.00, 0.2553), (4.20, 0.2488),
+    (4.40, 0.2429), (4.60, 0.2376), (4.80, 0.2326), (5.00, 0.2280),
+];
+
+const G6: &[(f64, f64)] = &[
+    (0.00, 0.2617), (0.05, 0.2553), (0.10, 0.2491), (0.15, 0.2432),
+    (0.20, 0.2376), (0.25, 0.2324), (0.30, 0.2278), (0.35, 0.2238),
+    (0.40, 0.2205), (0.45, 0.2177), (0.50, 0.2155), (0.55, 0.2138),
+    (0.60, 0.2126), (0.65, 0.2121), (0.70, 0.2122), (0.75, 0.2132),
+    (0.80, 0.2154), (0.85, 0.2194), (0.875, 0.2229), (0.90, 0.2297),
+    (0.925, 0.2449), (0.95, 0.2732), (0.975, 0.3141), (1.00, 0.3597),
+    (1.025, 0.3994), (1.05, 0.4261), (1.075, 0.4402), (1.10, 0.4465),
+    (1.125, 0.4490), (1.15, 0.4497), (1.175, 0.4494), (1.20, 0.4482),
+    (1.225, 0.4464), (1.25, 0.4441), (1.30, 0.4390), (1.35, 0.4336),
+    (1.40, 0.4279), (1.45, 0.4221), (1.50, 0.4162), (1.55, 0.4102),
+    (1.60, 0.4042), (1.65, 0.3981), (1.70, 0.3919), (1.75, 0.3855),
+    (1.80, 0.3788), (1.85, 0.3721), (1.90, 0.3652), (1.95, 0.3583),
+    (2.00, 0.3515), (2.05, 0.3447), (2.10, 0.3381), (2.15, 0.3314),
+    (2.20, 0.3249), (2.25, 0.3185), (2.30, 0.3122), (2.35, 0.3060),
+    (2.40, 0.3000), (2.45, 0.2941), (2.50, 0.2883), (2.60, 0.2772),
+    (2.70, 0.2668), (2.80, 0.2574), (2.90, 0.2487), (3.00, 0.2407),
+    (3.10, 0.2333), (3.20, 0.2265), (3.30, 0.2202), (3.40, 0.2144),
+    (3.50, 0.2089), (3.60, 0.2039), (3.70, 0.1991), (3.80, 0.1947),
+    (3.90, 0.1905), (4.00, 0.1866), (4.20, 0.1794), (4.40, 0.1730),
+    (4.60, 0.1673), (4.80, 0.1621), (5.00, 0.1574),
+];
+
+#[allow(clippy::approx_constant)]
+const G7: &[(f64, f64)] = &[
+    (0.00, 0.1198), (0.05, 0.1197), (0.10, 0.1196), (0.15, 0.1194),
+    (0.20, 0.1193), (0.25, 0.1194), (0.30, 0.1194), (0.35, 0.1194),
+    (0.40, 0.1193), (0.45, 0.1193), (0.50, 0.1194), (0.55, 0.1193),
+    (0.60, 0.1194), (0.65, 0.1197), (0.70, 0.1202), (0.725, 0.1207),
+    (0.75, 0.1215), (0.775, 0.1226), (0.80, 0.1242), (0.825, 0.1266),
+    (0.85, 0.1306), (0.875, 0.1368), (0.90, 0.1464), (0.925, 0.1660),
+    (0.95, 0.2054), (0.975, 0.2993), (1.00, 0.3803), (1.025, 0.4015),
+    (1.05, 0.4043), (1.075, 0.4034), (1.10, 0.4014), (1.125, 0.3987),
+    (1.15, 0.3955), (1.20, 0.3884), (1.25, 0.3810), (1.30, 0.3732),
+    (1.35, 0.3657), (1.40, 0.3580), (1.50, 0.3440), (1.55, 0.3376),
+    (1.60, 0.3315), (1.65, 0.3260), (1.70, 0.3209), (1.75, 0.3160),
+    (1.80, 0.3117), (1.85, 0.3078), (1.90, 0.3042), (1.95, 0.3010),
+    (2.00, 0.2980), (2.05, 0.2951), (2.10, 0.2922), (2.15, 0.2892),
+    (2.20, 0.2864), (2.25, 0.2835), (2.30, 0.2807), (2.35, 0.2779),
+    (2.40, 0.2752), (2.45, 0.2725), (2.50, 0.2697), (2.55, 0.2670),
+    (2.60, 0.2643), (2.65, 0.2615), (2.70, 0.2588), (2.75, 0.2561),
+    (2.80, 0.2533), (2.85, 0.2506), (2.90, 0.2479), (2.95, 0.2451),
+    (3.00, 0.2424), (3.10, 0.2368), (3.20, 0.2313), (3.30, 0.2258),
+    (3.40, 0.2205), (3.50, 0.2154), (3.60, 0.2106), (3.70, 0.2060),
+    (3.80, 0.2017), (3.90, 0.1975), (4.00, 0.1935), (4.20, 0.1861),
+    (4.40, 0.1793), (4.60, 0.1730), (4.80, 0.1672), (5.00, 0.1618),
+];
+
+const G8: &[(f64, f64)] = &[
+    (0.00, 0.2105), (0.05, 0.2105), (0.10, 0.2104), (0.15, 0.2104),
+    (0.20, 0.2103), (0.25, 0.2103), (0.30, 0.2103), (0.35, 0.2103),
+    (0.40, 0.2103), (0.45, 0.2102), (0.50, 0.2102), (0.55, 0.2102),
+    (0.60, 0.2102), (0.65, 0.2102), (0.70, 0.2103), (0.75, 0.2103),
+    (0.80, 0.2104), (0.825, 0.2104), (0.85, 0.2105), (0.875, 0.2106),
+    (0.90, 0.2109), (0.925, 0.2183), (0.95, 0.2571), (0.975, 0.3358),
+    (1.00, 0.4068), (1.025, 0.4378), (1.05, 0.4476), (1.075, 0.4493),
+    (1.10, 0.4477), (1.125, 0.4450), (1.15, 0.4419), (1.20, 0.4353),
+    (1.25, 0.4283), (1.30, 0.4208), (1.35, 0.4133), (1.40, 0.4059),
+    (1.45, 0.3986), (1.50, 0.3915), (1.55, 0.3845), (1.60, 0.3777),
+    (1.65, 0.3710), (1.70, 0.3645), (1.75, 0.3581), (1.80, 0.3519),
+    (1.85, 0.3458), (1.90, 0.3400), (1.95, 0.3343), (2.00, 0.3288),
+    (2.05, 0.3234), (2.10, 0.3182), (2.15, 0.3131), (2.20, 0.3081),
+    (2.25, 0.3032), (2.30, 0.2983), (2.35, 0.2937), (2.40, 0.2891),
+    (2.45, 0.2845), (2.50, 0.2802), (2.60, 0.2720), (2.70, 0.2642),
+    (2.80, 0.2569), (2.90, 0.2499), (3.00, 0.2432), (3.10, 0.2368),
+    (3.20, 0.2308), (3.30, 0.2251), (3.40, 0.2197), (3.50, 0.2147),
+    (3.60, 0.2101), (3.70, 0.2058), (3.80, 0.2019), (3.90, 0.1983),
+    (4.00, 0.1950), (4.20, 0.1890), (4.40, 0.1837), (4.60, 0.1791),
+    (4.80, 0.1750), (5.00, 0.1713),
+];
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::standard_atmosphere::StandardAtmosphere;
+    use crate::integrators::RungeKutta4;
+
+    const SIGHT_HEIGHT: f64 = 0.04;
+
+    // 175 grain .308 match bullet at 790 m/s
+    fn bullet() -> Projectile {
+        let mut bullet = Projectile::bullet(175. * units::GR,
+            0.308 * units::IN, 0.243 * units::LB / (units::IN * units::IN),
+            DragFunction::G7.table());
+        bullet.set_speed_theta_phi(790., 0., 0.);
+        bullet
+    }
+
+    #[test]
+    fn zeroed_bullet_crosses_the_line_of_sight_at_the_zero_range() {
+        let atm = StandardAtmosphere::new(0., Vector3::new(0., 0., 0.));
+        let mut bullet = bullet();
+        let theta = bullet.zero_elevation(&atm, &RungeKutta4, 1e-3,
+            SIGHT_HEIGHT, 100.).unwrap();
+        assert!(theta > 0. && theta < 1. * units::DEGREES);
+
+        bullet.set_speed_theta_phi(790., theta, 0.);
+        let rows = bullet.range_table(&atm, &RungeKutta4, 1e-3,
+            SIGHT_HEIGHT, 50., 300.);
+        assert_eq!(rows.len(), 6);
+        assert!((rows[1].range - 100.).abs() < 1e-12);
+        assert!(rows[1].drop.abs() < 1e-4);
+        // Above the line of sight between the crossings, below past zero
+        assert!(rows[0].drop < 0.);
+        assert!(rows[5].drop > rows[3].drop && rows[3].drop > 0.);
+        for pair in rows.windows(2) {
+            assert!(pair[1].time > pair[0].time);
+            assert!(pair[1].velocity < pair[0].velocity);
+            assert!(pair[1].energy < pair[0].energy);
+        }
+        assert!(rows.iter().all(|row| row.windage.abs() < 1e-9));
+    }
+
+    #[test]
+    fn zero_out_of_reach_is_none() {
+        let atm = StandardAtmosphere::new(0., Vector3::new(0., 0., 0.));
+        let mut bullet = bullet();
+        bullet.set_speed_theta_phi(50., 0., 0.);
+        assert!(bullet.zero_elevation(&atm, &RungeKutta4, 1e-3,
+            SIGHT_HEIGHT, 2000.).is_none());
+    }
+}
@@ -0,0 +1,309 @@
+// Fitting aerodynamic parameters to measured shots
+//
+// Each measured shot is simulated from its launch conditions and the
+// carry, apex height, landing angle and side deviation are compared with
+// the measurements. The parameters minimize the sum of the squared
+// differences, each divided by its scale in ResidualScales, by
+// Levenberg-Marquardt (see optimization::levenberg_marquardt)
+// Shots land at launch height, side is measured from the x axis,
+// positive right as in ShotParameters
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::si_units as units;
+use crate::atmosphere::AtmosphereModel;
+use crate::integrators::Integrator;
+use crate::aerodynamics::LinearCoefficients;
+use crate::projectiles::Projectile;
+use crate::shot_parameters::ShotParameters;
+use crate::optimization;
+use crate::parse::{self, ParseError};
+
+// Longest flight considered (s)
+const MAX_FLIGHT_TIME: f64 = 60.;
+const COST_TOL: f64 = 1e-6;
+const MAX_ITER: usize = 100;
+
+// Where a shot came down, measured or simulated
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct ShotOutcome {
+    pub carry: f64,
+    // Highest point above launch height
+    pub apex: f64,
+    // Angle of descent below the horizontal
+    pub landing_angle: f64,
+    // Landing point's distance right of the x axis
+    pub side: f64,
+    _private: (),
+}
+
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct MeasuredShot {
+    pub shot: ShotParameters,
+    pub outcome: ShotOutcome,
+    _private: (),
+}
+
+// Size of a residual worth one unit of cost for each quantity,
+// e.g. the measurement uncertainty
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct ResidualScales {
+    pub carry: f64,
+    pub apex: f64,
+    pub landing_angle: f64,
+    pub side: f64,
+    _private: (),
+}
+
+// Sets fitted parameters on a projectile
+pub type ApplyFn = dyn Fn(&mut Projectile, &[f64]) + Send + Sync;
+
+// Parameters to fit and how they set up the projectile
+#[derive(Clone)]
+pub enum FitParameters {
+    // [drag_coefficient, magnus_coefficient], with no aero_model
+    Coefficients,
+    // [drag_offset, drag_slope, lift_offset, lift_slope] of a
+    // LinearCoefficients aero_model
+    LinearCoefficients,
+    // Any parameters, the function sets them on the projectile
+    Custom(Arc<ApplyFn>),
+}
+
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Clone)]
+pub struct Calibration {
+    pub parameters: Vec<f64>,
+    pub standard_errors: Vec<f64>,
+    // Simulated minus measured, one per shot
+    pub residuals: Vec<ShotOutcome>,
+    // Root mean square of the residuals of each quantity
+    pub rms: ShotOutcome,
+    // Sum of the squared scaled residuals
+    pub cost: f64,
+    // Cost per degree of freedom, about 1 for a good fit with scales
+    // equal to the measurement uncertainties
+    pub reduced_chi_squared: f64,
+    pub iterations: usize,
+    pub converged: bool,
+    _private: (),
+}
+
+impl ShotOutcome {
+    pub fn new(carry: f64, apex: f64, landing_angle: f64, side: f64)
+        -> ShotOutcome {
+        ShotOutcome{ carry, apex, landing_angle, side, _private: () }
+    }
+
+    pub fn difference(&self, other: &ShotOutcome) -> ShotOutcome {
+        ShotOutcome::new(self.carry - other.carry, self.apex - other.apex,
+            self.landing_angle - other.landing_angle, self.side - other.side)
+    }
+}
+
+impl MeasuredShot {
+    pub fn new(shot: ShotParameters, outcome: ShotOutcome) -> MeasuredShot {
+        MeasuredShot{ shot, outcome, _private: () }
+    }
+
+    // CSV with one shot per line in launch monitor units: ball speed (mph),
+    // launch angle (deg), launch direction (deg), total spin (rpm),
+    // spin axis (deg), carry (yd), apex (ft), landing angle (deg), side (yd)
+    pub fn from_csv<P: AsRef<Path>>(path: P)
+        -> Result<Vec<MeasuredShot>, ParseError> {
+        let text = fs::read_to_string(path)?;
+        MeasuredShot::from_csv_str(&text)
+    }
+
+    pub fn from_csv_str(text: &str) -> Result<Vec<MeasuredShot>, ParseError> {
+        let rows = parse::csv_numbers(text)?;
+        // Every row has as many columns as the first one
+        if let (Some((line, _)), Some(row)) =
+            (parse::data_lines(text).next(), rows.first()) {
+            if row.len() != 9 {
+                return Err(ParseError::InvalidFormat{ line, message: format!(
+                    "expected 9 columns, found {}", row.len()) });
+            }
+        }
+        Ok(rows.iter().map(|row| {
+            let shot = ShotParameters::from_monitor(row[0], row[1], row[2],
+                row[3], row[4]);
+            MeasuredShot::new(shot, ShotOutcome::new(row[5] * units::YD,
+                row[6] * units::FT, row[7] * units::DEGREES, row[8] * units::YD))
+        }).collect())
+    }
+}
+
+impl ResidualScales {
+    pub fn new(carry: f64, apex: f64, landing_angle: f64, side: f64)
+        -> ResidualScales {
+        ResidualScales{ carry, apex, landing_angle, side, _private: () }
+    }
+}
+
+impl Default for ResidualScales {
+    // 1 m for the distances and 1 degree for the landing angle
+    fn default() -> ResidualScales {
+        ResidualScales::new(units::M, units::M, units::DEGREES, units::M)
+    }
+}
+
+impl FitParameters {
+    // Number of parameters, None for Custom
+    pub fn n_parameters(&self) -> Option<usize> {
+        match self {
+            FitParameters::Coefficients => Some(2),
+            FitParameters::LinearCoefficients => Some(4),
+            FitParameters::Custom(_) => None,
+        }
+    }
+
+    // parameters must hold n_parameters() values
+    pub fn apply(&self, projectile: &mut Projectile, parameters: &[f64]) {
+        match self {
+            FitParameters::Coefficients => {
+                projectile.aero_model = None;
+                projectile.drag_coefficient = parameters[0];
+                projectile.magnus_coefficient = parameters[1];
+            },
+            FitParameters::LinearCoefficients => {
+                projectile.aero_model = Some(Arc::new(LinearCoefficients::new(
+                    parameters[0], parameters[1], parameters[2], parameters[3])));
+            },
+            FitParameters::Custom(apply) => apply(projectile, parameters),
+        }
+    }
+}
+
+impl Projectile {
+    // Outcome of a shot from the projectile's position,
+    // None if it does not come back down to launch height
+    pub fn simulate_shot(
+        &self, atm: &dyn AtmosphereModel, integrator: &dyn Integrator,
+        t_step: f64, shot: &ShotParameters) -> Option<ShotOutcome> {
+
+        let mut ball = self.clone();
+        ball.set_shot(shot);
+        let result = ball.trajectory(atm, integrator, t_step,
+            self.position.z, MAX_FLIGHT_TIME);
+        result.landing?;
+        let delta = result.last().position - self.position;
+        Some(ShotOutcome::new(result.carry(),
+            result.apex_height() - self.position.z,
+            result.landing_angle(), -delta.y))
+    }
+
+    // Least squares fit of the parameters to the measured shots, starting
+    // from initial, None if initial does not hold the number of parameters
+    // of fit or a shot cannot be simulated at the start
+    // The fit is unconstrained, start near realistic values
+    #[allow(clippy::too_many_arguments)]
+    pub fn calibrate(
+        &self, atm: &dyn AtmosphereModel, integrator: &dyn Integrator,
+        t_step: f64, shots: &[MeasuredShot], fit: &FitParameters,
+        initial: &[f64], scales: &ResidualScales) -> Option<Calibration> {
+
+        if fit.n_parameters().is_some_and(|n| n != initial.len()) {
+            return None;
+        }
+        let residuals = |parameters: &[f64]| -> Option<Vec<ShotOutcome>> {
+            let mut ball = self.clone();
+            fit.apply(&mut ball, parameters);
+            shots.iter().map(|measured| {
+                let simulated = ball.simulate_shot(atm, integrator, t_step,
+                    &measured.shot)?;
+                Some(simulated.difference(&measured.outcome))
+            }).collect()
+        };
+        let scaled = |parameters: &[f64]| -> Option<Vec<f64>> {
+            Some(residuals(parameters)?.iter().flat_map(|r| [
+                r.carry / scales.carry, r.apex / scales.apex,
+                r.landing_angle / scales.landing_angle, r.side / scales.side,
+            ]).collect())
+        };
+
+        let fitted = optimization::levenberg_marquardt(scaled, initial,
+            COST_TOL, MAX_ITER)?;
+        let shot_residuals = residuals(&fitted.parameters)?;
+
+        let n = shot_residuals.len() as f64;
+        let rms = |value: &dyn Fn(&ShotOutcome) -> f64| {
+            (shot_residuals.iter().map(|r| value(r).powi(2)).sum::<f64>() / n).sqrt()
+        };
+        let dof = fitted.residuals.len() as f64 - initial.len() as f64;
+        Some(Calibration{
+            rms: ShotOutcome::new(rms(&|r| r.carry), rms(&|r| r.apex),
+                rms(&|r| r.landing_angle), rms(&|r| r.side)),
+            residuals: shot_residuals,
+            cost: fitted.cost,
+            reduced_chi_squared: if dof > 0. { fitted.cost / dof } else { f64::NAN },
+            parameters: fitted.parameters,
+            standard_errors: fitted.standard_errors,
+            iterations: fitted.iterations,
+            converged: fitted.converged,
+            _private: () })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::standard_atmosphere::StandardAtmosphere;
+    use crate::integrators::RungeKutta4;
+    use crate::projectile_builder::ProjectileBuilder;
+    use crate::vector3::Vector3;
+
+    const T_STEP: f64 = 0.01;
+
+    // Shots measured exactly from a ball with known coefficients
+    #[test]
+    fn calibrate_recovers_the_coefficients_of_synthetic_shots() {
+        let atm = StandardAtmosphere::new(0., Vector3::new(0., 0., 0.));
+        let fit = FitParameters::Coefficients;
+        let truth = [0.25, 4e-5];
+        let mut ball = ProjectileBuilder::golf_ball().build().unwrap();
+        fit.apply(&mut ball, &truth);
+
+        let shots: Vec<MeasuredShot> = [
+            (150., 11., 0., 2_600., 0.),
+            (130., 14., 2., 4_000., -5.),
+            (100., 20., -1., 7_000., 8.),
+        ].iter().map(|&(speed, angle, direction, spin, axis)| {
+            let shot = ShotParameters::from_monitor(speed, angle, direction,
+                spin, axis);
+            MeasuredShot::new(shot,
+                ball.simulate_shot(&atm, &RungeKutta4, T_STEP, &shot).unwrap())
+        }).collect();
+
+        let calibration = ball.calibrate(&atm, &RungeKutta4, T_STEP, &shots,
+            &fit, &[0.3, 3e-5], &ResidualScales::default()).unwrap();
+        assert!(calibration.converged);
+        assert!((calibration.parameters[0] - truth[0]).abs() < 1e-4);
+        assert!((calibration.parameters[1] / truth[1] - 1.).abs() < 1e-3);
+        assert!(calibration.rms.carry < 1e-2);
+        assert!(ball.calibrate(&atm, &RungeKutta4, T_STEP, &shots,
+            &fit, &[0.3], &ResidualScales::default()).is_none());
+    }
+
+    #[test]
+    fn csv_needs_nine_columns() {
+        let shots = MeasuredShot::from_csv_str("# launch monitor export\n\
+            150, 11, 0, 2600, 0, 240, 95, 40, 1\n\
+            130, 14, 2, 4000, -5, 210, 90, 45, -3\n").unwrap();
+        assert_eq!(shots.len(), 2);
+        assert!((shots[1].outcome.carry - 210. * units::YD).abs() < 1e-12);
+        assert!((shots[1].outcome.side + 3. * units::YD).abs() < 1e-12);
+
+        let err = MeasuredShot::from_csv_str("\n150, 11, 0, 2600, 0, 240, 95, 40\n");
+        assert!(matches!(err, Err(ParseError::InvalidFormat{ line: 2, .. })));
+        let err = MeasuredShot::from_csv_str("150, 11, 0, 2600, 0, 240, 95, 40, 1\n\
+            130, 14, 2, 4000, -5, 210, 90, 45\n");
+        assert!(matches!(err, Err(ParseError::InvalidFormat{ line: 2, .. })));
+    }
+}
@@ -18,10 +18,13 @@ pub const PRESSURE_SEA_LEVEL:f64 = 101_325. * units::PA;
 pub const AIR_MOLEC_MASS:f64 = 4.81e-26 * units::KG;
 // Molar mass for air (kg/mol)
 pub const AIR_MOL_MASS:f64 = 0.028_964_4 * units::KG / units::MOL;
-// Molar mass for water vapor (kg)
-pub const WATER_VAPOR_MOL_MASS:f64 = 4.81e-26 * units::KG;
+// Molar mass for water vapor (kg/mol)
+pub const WATER_VAPOR_MOL_MASS:f64 = 0.018_015_28 * units::KG / units::MOL;
 
 // Gas constant (J/(mol*K))
 pub const R:f64 = 8.314_462_618_153_24 * units::J / (units::MOL * units::K);
 // Specific Gas constant for dry air  (J/(mol*K))
 pub const R_SPEC_DRY_AIR:f64 = 287.050_067_6 * units::J / (units::MOL * units::K);
+
+// Adiabatic index (heat capacity ratio) of dry air
+pub const AIR_ADIABATIC_INDEX:f64 = 1.4;
@@ -0,0 +1,284 @@
+// Monte Carlo shot dispersion
+//
+// Each shot draws its launch and wind from a ShotVariation and flies to
+// the launch height. Shot i always uses the same random stream for a
+// given seed, so a batch is reproducible whatever the number of threads
+
+use std::thread;
+use libm::{atan2, cos, sin};
+
+use crate::vector3::Vector3;
+use crate::atmosphere::AtmosphereModel;
+use crate::integrators::Integrator;
+use crate::projectiles::Projectile;
+use crate::random::{Distribution, Rng};
+
+// Longest flight considered (s)
+const MAX_FLIGHT_TIME: f64 = 60.;
+
+// Distributions of the launch and wind for each shot
+// Angles as in Projectile::set_speed_theta_phi and set_spin_rate_axis,
+// wind_direction is the azimuth the wind blows towards, the drawn wind
+// is added to the atmosphere's own wind
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct ShotVariation {
+    pub speed: Distribution,
+    pub theta: Distribution,
+    pub phi: Distribution,
+    pub spin_rate: Distribution,
+    pub spin_axis: Distribution,
+    pub wind_speed: Distribution,
+    pub wind_direction: Distribution,
+    _private: (),
+}
+
+// Landing points of a batch of shots, unlanded counts the shots still
+// in the air after MAX_FLIGHT_TIME, which are left out of the statistics
+#[allow(clippy::manual_non_exhaustive)]
+pub struct Dispersion {
+    pub start: Vector3,
+    pub landings: Vec<Vector3>,
+    pub carries: Vec<f64>,
+    pub unlanded: usize,
+    _private: (),
+}
+
+// Dispersion ellipse in the horizontal plane, orientation is the
+// azimuth of the major axis
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct Ellipse {
+    pub center: Vector3,
+    pub semi_major: f64,
+    pub semi_minor: f64,
+    pub orientation: f64,
+    _private: (),
+}
+
+// Atmosphere with one shot's random draw added to its wind
+struct SampledWind<'a> {
+    atm: &'a dyn AtmosphereModel,
+    wind: Vector3,
+}
+
+impl AtmosphereModel for SampledWind<'_> {
+    fn reference_elevation(&self) -> f64 {
+        self.atm.reference_elevation()
+    }
+
+    fn temperature_at(&self, altitude: f64) -> f64 {
+        self.atm.temperature_at(altitude)
+    }
+
+    fn pressure_at(&self, altitude: f64) -> f64 {
+        self.atm.pressure_at(altitude)
+    }
+
+    fn density_at(&self, altitude: f64) -> f64 {
+        self.atm.density_at(altitude)
+    }
+
+    fn dynamic_viscosity_at(&self, altitude: f64) -> f64 {
+        self.atm.dynamic_viscosity_at(altitude)
+    }
+
+    fn speed_of_sound_at(&self, altitude: f64) -> f64 {
+        self.atm.speed_of_sound_at(altitude)
+    }
+
+    fn wind(&self, position: Vector3, time: f64) -> Vector3 {
+        self.atm.wind(position, time) + self.wind
+    }
+}
+
+impl ShotVariation {
+    pub fn new(speed: Distribution, theta: Distribution, phi: Distribution,
+        spin_rate: Distribution, spin_axis: Distribution,
+        wind_speed: Distribution, wind_direction: Distribution)
+        -> ShotVariation {
+        ShotVariation{ speed, theta, phi, spin_rate, spin_axis,
+            wind_speed, wind_direction, _private: () }
+    }
+
+    // Launch the projectile and its atmosphere with one random draw
+    fn sample<'a>(&self, projectile: &Projectile, atm: &'a dyn AtmosphereModel,
+        rng: &mut Rng) -> (Projectile, SampledWind<'a>) {
+        let mut shot = projectile.clone();
+        let speed = self.speed.sample(rng);
+        let theta = self.theta.sample(rng);
+        let phi = self.phi.sample(rng);
+        shot.set_speed_theta_phi(speed, theta, phi);
+        shot.set_spin_rate_axis(self.spin_rate.sample(rng),
+            self.spin_axis.sample(rng));
+
+        let wind_speed = self.wind_speed.sample(rng);
+        let wind_direction = self.wind_direction.sample(rng);
+        let wind = Vector3::new(wind_speed * cos(wind_direction),
+            wind_speed * sin(wind_direction), 0.);
+        (shot, SampledWind{ atm, wind })
+    }
+}
+
+impl Dispersion {
+    pub fn mean_landing(&self) -> Vector3 {
+        let n = self.landings.len() as f64;
+        self.landings.iter().fold(Vector3::new(0., 0., 0.), |sum, p| sum + *p)
+            * (1. / n)
+    }
+
+    // Sample covariance of the horizontal landing coordinates,
+    // [[var x, cov xy], [cov xy, var y]]
+    pub fn covariance(&self) -> [[f64; 2]; 2] {
+        let mean = self.mean_landing();
+        let n = self.landings.len() as f64;
+        let mut cov = [[0.; 2]; 2];
+        for p in &self.landings {
+            let d = [p.x - mean.x, p.y - mean.y];
+            for (i, row) in cov.iter_mut().enumerate() {
+                for (j, c) in row.iter_mut().enumerate() {
+                    *c += d[i] * d[j] / (n - 1.);
+                }
+            }
+        }
+        cov
+    }
+
+    // Ellipse n_sigma standard deviations out along the principal axes,
+    // for normal scatter 1 and 2 sigma hold about 39% and 86% of shots
+    pub fn ellipse(&self, n_sigma: f64) -> Ellipse {
+        let [[a, b], [_, c]] = self.covariance();
+        let mid = (a + c) / 2.;
+        let radius = (((a - c) / 2.).powi(2) + b.powi(2)).sqrt();
+        Ellipse{ center: self.mean_landing(),
+            semi_major: n_sigma * (mid + radius).sqrt(),
+            semi_minor: n_sigma * (mid - radius).max(0.).sqrt(),
+            orientation: atan2(2. * b, a - c) / 2.,
+            _private: () }
+    }
+
+    pub fn carry_mean(&self) -> f64 {
+        self.carries.iter().sum::<f64>() / self.carries.len() as f64
+    }
+
+    pub fn carry_std_dev(&self) -> f64 {
+        let mean = self.carry_mean();
+        let n = self.carries.len() as f64;
+        (self.carries.iter().map(|c| (c - mean).powi(2)).sum::<f64>()
+            / (n - 1.)).sqrt()
+    }
+
+    // Carry below which the given percentage (0 to 100) of shots land,
+    // interpolating linearly between shots
+    pub fn carry_percentile(&self, percent: f64) -> f64 {
+        let mut sorted = self.carries.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let rank = (percent / 100.).clamp(0., 1.) * (sorted.len() - 1) as f64;
+        let lower = rank.floor() as usize;
+        let upper = rank.ceil() as usize;
+        sorted[lower] + (rank - lower as f64) * (sorted[upper] - sorted[lower])
+    }
+}
+
+impl Projectile {
+    // Fly n_shots random variations of this projectile from its current
+    // position, spread over the available threads
+    // Panics if n_shots is less than 2, the statistics need two shots
+    pub fn dispersion(
+        &self, atm: &dyn AtmosphereModel, integrator: &dyn Integrator,
+        t_step: f64, variation: &ShotVariation, n_shots: usize,
+        seed: u64) -> Dispersion {
+
+        let n_threads = thread::available_parallelism().map_or(1, |n| n.get());
+        self.dispersion_on_threads(atm, integrator, t_step, variation,
+            n_shots, seed, n_threads)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn dispersion_on_threads(
+        &self, atm: &dyn AtmosphereModel, integrator: &dyn Integrator,
+        t_step: f64, variation: &ShotVariation, n_shots: usize,
+        seed: u64, n_threads: usize) -> Dispersion {
+
+        assert!(n_shots >= 2, "n_shots must be at least 2, got {}", n_shots);
+        let fly = |index: usize| {
+            let mut rng = Rng::stream(seed, index as u64);
+            let (mut shot, shot_atm) = variation.sample(self, atm, &mut rng);
+            let result = shot.trajectory(&shot_atm, integrator, t_step,
+                self.position.z, MAX_FLIGHT_TIME);
+            result.landing?;
+            Some((result.last().position, result.carry()))
+        };
+
+        let n_threads = n_threads.clamp(1, n_shots);
+        let chunk = n_shots.div_ceil(n_threads);
+        let shots: Vec<Option<(Vector3, f64)>> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..n_threads).map(|k| {
+                let fly = &fly;
+                scope.spawn(move || {
+                    (k * chunk..((k + 1) * chunk).min(n_shots))
+                        .map(fly).collect::<Vec<_>>()
+                })
+            }).collect();
+            handles.into_iter()
+                .flat_map(|handle| handle.join().unwrap()).collect()
+        });
+
+        let unlanded = shots.iter().filter(|shot| shot.is_none()).count();
+        let (landings, carries) = shots.into_iter().flatten().unzip();
+        Dispersion{ start: self.position, landings, carries, unlanded,
+            _private: () }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::standard_atmosphere::StandardAtmosphere;
+    use crate::integrators::RungeKutta4;
+    use crate::projectile_builder::ProjectileBuilder;
+
+    fn variation() -> ShotVariation {
+        ShotVariation::new(
+            Distribution::Normal{ mean: 60., std_dev: 2. },
+            Distribution::Normal{ mean: 0.25, std_dev: 0.02 },
+            Distribution::Normal{ mean: 0., std_dev: 0.02 },
+            Distribution::Fixed(0.),
+            Distribution::Fixed(0.),
+            Distribution::Uniform{ min: 0., max: 3. },
+            Distribution::Uniform{ min: 0., max: 2. * std::f64::consts::PI })
+    }
+
+    fn same(p: Vector3, q: Vector3) -> bool {
+        p.x == q.x && p.y == q.y && p.z == q.z
+    }
+
+    fn batch(seed: u64, n_threads: usize) -> Dispersion {
+        let atm = StandardAtmosphere::new(0., Vector3::new(0., 0., 0.));
+        let ball = ProjectileBuilder::golf_ball().build().unwrap();
+        ball.dispersion_on_threads(&atm, &RungeKutta4, 0.01, &variation(),
+            9, seed, n_threads)
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_landings() {
+        let (a, b) = (batch(7, 2), batch(7, 2));
+        assert_eq!(a.unlanded, 0);
+        assert_eq!(a.landings.len(), 9);
+        assert!(a.landings.iter().zip(&b.landings).all(|(p, q)| same(*p, *q)));
+        assert_eq!(a.carries, b.carries);
+        assert!(batch(8, 2).carries != a.carries);
+    }
+
+    #[test]
+    fn landings_do_not_depend_on_the_number_of_threads() {
+        let single = batch(7, 1);
+        for n_threads in [2, 4, 16] {
+            let split = batch(7, n_threads);
+            assert!(single.landings.iter().zip(&split.landings)
+                .all(|(p, q)| same(*p, *q)));
+            assert_eq!(single.carries, split.carries);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+// Trajectory coordinates fixed to the rotating Earth
+//
+// The frame is a flat local tangent plane at the launch site: z up,
+// x along the compass bearing x_bearing and y 90 degrees anticlockwise
+// of x. Earth's rotation adds the Coriolis acceleration, which includes
+// the Eotvos effect, and the change in centrifugal acceleration away
+// from the origin, the centrifugal acceleration at the origin being
+// part of the local gravity
+// https://en.wikipedia.org/wiki/Coriolis_force#Applied_to_Earth
+
+use libm::{cos, sin};
+
+use crate::vector3::Vector3;
+use crate::constants;
+use crate::si_units as units;
+
+// Earth's rotation rate (rad/s)
+pub const EARTH_ROTATION_RATE: f64 = 7.292_115e-5 * units::RAD / units::S;
+
+// WGS84 ellipsoid and normal gravity constants
+// https://earth-info.nga.mil/php/download.php?file=coord-wgs84
+const WGS84_SEMI_MAJOR_AXIS: f64 = 6_378_137. * units::M;
+const WGS84_FLATTENING: f64 = 1. / 298.257_223_563;
+const WGS84_ECCENTRICITY_SQ: f64 = 6.694_379_990_14e-3;
+const WGS84_EQUATOR_GRAVITY: f64 = 9.780_325_335_9 * units::M / (units::S * units::S);
+const WGS84_SOMIGLIANA_K: f64 = 1.931_852_652_41e-3;
+// omega^2 a^2 b / (G M)
+const WGS84_M: f64 = 3.449_786_506_84e-3;
+
+#[derive(Copy, Clone, Default)]
+pub enum GravityModel {
+    // constants::G everywhere
+    #[default]
+    Standard,
+    // WGS84 normal gravity at the latitude and altitude
+    Wgs84,
+}
+
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct EarthFrame {
+    // Geodetic latitude, positive north (rad)
+    pub latitude: f64,
+    // Compass bearing of the x axis (rad)
+    pub x_bearing: f64,
+    pub gravity_model: GravityModel,
+    _private: (),
+}
+
+impl EarthFrame {
+    pub fn new(latitude: f64, x_bearing: f64, gravity_model: GravityModel)
+        -> EarthFrame {
+        EarthFrame{ latitude, x_bearing, gravity_model, _private: () }
+    }
+
+    // Earth's angular velocity in frame coordinates
+    pub fn rotation(&self) -> Vector3 {
+        // Along north and up, then onto x and y
+        let north = EARTH_ROTATION_RATE * cos(self.latitude);
+        Vector3::new(north * cos(self.x_bearing), north * sin(self.x_bearing),
+            EARTH_ROTATION_RATE * sin(self.latitude))
+    }
+
+    // Magnitude of gravity at an altitude above sea level
+    pub fn gravity(&self, altitude: f64) -> f64 {
+        match self.gravity_model {
+            GravityModel::Standard => constants::G,
+            GravityModel::Wgs84 => {
+                // Somigliana's formula and the second order
+                // altitude correction
+                let sin2 = sin(self.latitude).powi(2);
+                let surface = WGS84_EQUATOR_GRAVITY
+                    * (1. + WGS84_SOMIGLIANA_K * sin2)
+                    / (1. - WGS84_ECCENTRICITY_SQ * sin2).sqrt();
+                let a = WGS84_SEMI_MAJOR_AXIS;
+                surface * (1. - 2. / a * (1. + WGS84_FLATTENING + WGS84_M
+                    - 2. * WGS84_FLATTENING * sin2) * altitude
+                    + 3. * altitude.powi(2) / a.powi(2))
+            },
+        }
+    }
+
+    // -2 Omega x v
+    pub fn coriolis(&self, velocity: Vector3) -> Vector3 {
+        Vector3::cross_prod(&self.rotation(), &velocity) * -2.
+    }
+
+    // -Omega x (Omega x r), relative to the origin
+    pub fn centrifugal(&self, position: Vector3) -> Vector3 {
+        let rotation = self.rotation();
+        Vector3::cross_prod(&rotation,
+            &Vector3::cross_prod(&rotation, &position)) * -1.
+    }
+}
@@ -0,0 +1,151 @@
+use crate::vector3::Vector3;
+use crate::integrators::{Derivative, DerivativeFn, State};
+use crate::root_finding;
+use crate::terrain::Terrain;
+
+// Tolerance on the event time (s) and iteration cap for the root finder
+const TIME_TOL: f64 = 1e-10;
+const MAX_ITER: usize = 100;
+
+// Event function g(t, y)
+pub type EventFn<'a> = dyn Fn(f64, &State) -> f64 + 'a;
+
+// Which sign changes of the event function count as a crossing
+#[derive(Copy, Clone, PartialEq)]
+pub enum Direction {
+    Rising,
+    Falling,
+    Either,
+}
+
+// Zero crossing of a scalar function of time and state, g(t, y) = 0
+// Terminal events stop the integration
+pub struct Event<'a> {
+    pub direction: Direction,
+    pub terminal: bool,
+    function: Box<EventFn<'a>>,
+    _private: (),
+}
+
+// Time and state at which an event occurred
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct EventHit {
+    pub time: f64,
+    pub position: Vector3,
+    pub velocity: Vector3,
+    pub spin: Vector3,
+    _private: (),
+}
+
+impl<'a> Event<'a> {
+    pub fn new<F>(function: F, direction: Direction, terminal: bool)
+        -> Event<'a>
+        where F: Fn(f64, &State) -> f64 + 'a {
+        Event{ direction, terminal, function: Box::new(function), _private: () }
+    }
+
+    // Projectile meets the terrain on the way down
+    pub fn landing_on(terrain: &'a dyn Terrain) -> Event<'a> {
+        Event::new(move |_t, state| {
+            state.position.z - terrain.height(state.position.x, state.position.y)
+        }, Direction::Falling, true)
+    }
+
+    // Vertical velocity changes from up to down
+    pub fn apex() -> Event<'a> {
+        Event::new(|_t, state| state.velocity.z, Direction::Falling, false)
+    }
+
+    pub fn value(&self, t: f64, state: &State) -> f64 {
+        (self.function)(t, state)
+    }
+
+    // Does the sign change from g0 to g1 match the event direction
+    pub fn crossed(&self, g0: f64, g1: f64) -> bool {
+        let rising = g0 < 0. && g1 >= 0.;
+        let falling = g0 > 0. && g1 <= 0.;
+        match self.direction {
+            Direction::Rising => rising,
+            Direction::Falling => falling,
+            Direction::Either => rising || falling,
+        }
+    }
+
+    // Refine a crossing bracketed by the step (t0, s0) -> (t1, s1)
+    // The state inside the step is a cubic Hermite interpolant,
+    // so no extra integration steps are needed
+    pub fn locate(&self, derivative: &DerivativeFn,
+        t0: f64, s0: &State, t1: f64, s1: &State) -> Option<EventHit> {
+
+        let d0 = derivative(t0, s0);
+        let d1 = derivative(t1, s1);
+        let interp = |t: f64| hermite(t0, s0, &d0, t1, s1, &d1, t);
+
+        let t = root_finding::brent(|t| self.value(t, &interp(t)),
+            t0, t1, TIME_TOL, MAX_ITER)?;
+        Some(EventHit::new(t, &interp(t)))
+    }
+}
+
+impl EventHit {
+    pub fn new(time: f64, state: &State) -> EventHit {
+        EventHit{ time, position: state.position, velocity: state.velocity,
+            spin: state.spin, _private: () }
+    }
+
+    pub fn state(&self) -> State {
+        State::new(self.position, self.velocity, self.spin)
+    }
+}
+
+// Cubic Hermite interpolation of each state component over one step
+// from its values and time derivatives at both ends
+fn hermite(t0: f64, s0: &State, d0: &Derivative,
+    t1: f64, s1: &State, d1: &Derivative, t: f64) -> State {
+
+    let h = t1 - t0;
+    let u = (t - t0) / h;
+    let h00 = 2. * u.powi(3) - 3. * u.powi(2) + 1.;
+    let h10 = u.powi(3) - 2. * u.powi(2) + u;
+    let h01 = -2. * u.powi(3) + 3. * u.powi(2);
+    let h11 = u.powi(3) - u.powi(2);
+
+    let cubic = |p0: Vector3, m0: Vector3, p1: Vector3, m1: Vector3| {
+        p0 * h00 + m0 * (h10 * h) + p1 * h01 + m1 * (h11 * h)
+    };
+    State::new(cubic(s0.position, d0.velocity, s1.position, d1.velocity),
+        cubic(s0.velocity, d0.acceleration, s1.velocity, d1.acceleration),
+        cubic(s0.spin, d0.spin_rate, s1.spin, d1.spin_rate))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::constants;
+    use crate::integrators::RungeKutta4;
+    use crate::projectiles::Projectile;
+
+    // Without air the flight is a parabola, which the Runge-Kutta steps
+    // and the Hermite interpolant reproduce exactly
+    #[test]
+    fn vacuum_landing_and_apex_match_the_parabola() {
+        let (speed, theta, end_height) = (30., 0.6, 2.);
+        let mut ball = Projectile::new();
+        ball.mass = 0.1;
+        ball.set_speed_theta_phi(speed, theta, 0.);
+        let result = ball.trajectory_vaccum(&RungeKutta4, 0.07, end_height, 10.);
+
+        let g = constants::G;
+        let vz = speed * theta.sin();
+        let apex_time = vz / g;
+        let landing_time = (vz + (vz.powi(2) - 2. * g * end_height).sqrt()) / g;
+        let landing = result.landing.unwrap();
+        let apex = result.apex.unwrap();
+        assert!((landing.time - landing_time).abs() < 1e-9);
+        assert!((landing.position.z - end_height).abs() < 1e-9);
+        assert!((landing.position.x - speed * theta.cos() * landing_time).abs() < 1e-9);
+        assert!((apex.time - apex_time).abs() < 1e-9);
+        assert!((apex.position.z - vz.powi(2) / (2. * g)).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,281 @@
+// Writing trajectories to CSV, JSON and 3D polyline files
+// (Wavefront OBJ and ASCII PLY), and reading them back from CSV
+//
+// Values are in SI units and written with full precision, so a CSV file
+// read back gives the same samples. Forces are not written, and samples
+// read from CSV have zero forces and NaN Mach and Reynolds numbers
+// https://en.wikipedia.org/wiki/Wavefront_.obj_file
+// https://en.wikipedia.org/wiki/PLY_(file_format)
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::vector3::Vector3;
+use crate::trajectory::{Forces, TrajectoryResult, TrajectorySample};
+use crate::ground::ShotResult;
+use crate::events::EventHit;
+use crate::parse::{self, ParseError};
+
+// Header of the CSV format, commented so that the parser skips it
+const CSV_HEADER: &str = "# time,x,y,z,vx,vy,vz,spin_x,spin_y,spin_z";
+const CSV_COLUMNS: usize = 10;
+
+impl TrajectoryResult {
+    // One line per sample: time, position, velocity and spin components
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{}\n", CSV_HEADER);
+        for s in &self.samples {
+            let columns = [s.time,
+                s.position.x, s.position.y, s.position.z,
+                s.velocity.x, s.velocity.y, s.velocity.z,
+                s.spin.x, s.spin.y, s.spin.z];
+            let line: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+            csv.push_str(&line.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_csv())
+    }
+
+    // Samples written by to_csv, the landing, apex and events are not kept
+    pub fn from_csv<P: AsRef<Path>>(path: P)
+        -> Result<TrajectoryResult, ParseError> {
+        let text = fs::read_to_string(path)?;
+        TrajectoryResult::from_csv_str(&text)
+    }
+
+    pub fn from_csv_str(text: &str) -> Result<TrajectoryResult, ParseError> {
+        let rows = parse::csv_numbers(text)?;
+        // Every row has as many columns as the first one
+        match (parse::data_lines(text).next(), rows.first()) {
+            (Some((line, _)), Some(row)) if row.len() != CSV_COLUMNS => {
+                return Err(ParseError::InvalidFormat{ line, message: format!(
+                    "expected {} columns, found {}", CSV_COLUMNS, row.len()) });
+            },
+            (None, _) | (_, None) => {
+                return Err(ParseError::InvalidFormat{ line: text.lines().count(),
+                    message: "no samples".to_string() });
+            },
+            _ => {},
+        }
+        let zero = Vector3::new(0., 0., 0.);
+        let samples = rows.iter().map(|row| {
+            TrajectorySample::new(row[0],
+                Vector3::new(row[1], row[2], row[3]),
+                Vector3::new(row[4], row[5], row[6]),
+                Vector3::new(row[7], row[8], row[9]),
+                Forces::new(zero, zero, zero), f64::NAN, f64::NAN)
+        }).collect();
+        Ok(TrajectoryResult::new(samples, None, None, None, Vec::new()))
+    }
+
+    // Object with the samples, landing, apex, events and error estimate
+    // Values that are not finite are written as null
+    pub fn to_json(&self) -> String {
+        let samples: Vec<String> = self.samples.iter().map(|s| format!(
+            "{{\"time\":{},\"position\":{},\"velocity\":{},\"spin\":{},\
+            \"mach\":{},\"reynolds\":{}}}",
+            json_number(s.time), json_vector(s.position), json_vector(s.velocity),
+            json_vector(s.spin), json_number(s.mach), json_number(s.reynolds)))
+            .collect();
+        let events: Vec<String> = self.events.iter().map(|(index, hit)| format!(
+            "{{\"index\":{},\"hit\":{}}}", index, json_hit(hit))).collect();
+        format!("{{\"samples\":[{}],\"landing\":{},\"apex\":{},\"events\":[{}],\
+            \"error_estimate\":{}}}",
+            samples.join(","),
+            self.landing.as_ref().map_or("null".to_string(), json_hit),
+            self.apex.as_ref().map_or("null".to_string(), json_hit),
+            events.join(","),
+            self.error_estimate.map_or("null".to_string(), json_number))
+    }
+
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+
+    // Polyline through the sample positions
+    pub fn to_obj(&self) -> String {
+        obj(&[self.positions()])
+    }
+
+    pub fn write_obj<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_obj())
+    }
+
+    pub fn to_ply(&self) -> String {
+        ply(&[self.positions()])
+    }
+
+    pub fn write_ply<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_ply())
+    }
+}
+
+impl ShotResult {
+    // One polyline per flight and one for the roll
+    pub fn to_obj(&self) -> String {
+        obj(&self.polylines())
+    }
+
+    pub fn write_obj<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_obj())
+    }
+
+    pub fn to_ply(&self) -> String {
+        ply(&self.polylines())
+    }
+
+    pub fn write_ply<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_ply())
+    }
+
+    fn polylines(&self) -> Vec<Vec<Vector3>> {
+        let mut lines: Vec<Vec<Vector3>> = self.flights.iter()
+            .map(|flight| flight.positions()).collect();
+        if !self.roll.is_empty() {
+            lines.push(self.roll.clone());
+        }
+        lines
+    }
+}
+
+fn json_number(value: f64) -> String {
+    if value.is_finite() { value.to_string() } else { "null".to_string() }
+}
+
+fn json_vector(v: Vector3) -> String {
+    format!("[{},{},{}]", json_number(v.x), json_number(v.y), json_number(v.z))
+}
+
+fn json_hit(hit: &EventHit) -> String {
+    format!("{{\"time\":{},\"position\":{},\"velocity\":{},\"spin\":{}}}",
+        json_number(hit.time), json_vector(hit.position),
+        json_vector(hit.velocity), json_vector(hit.spin))
+}
+
+// Polylines with at least two vertices, a single point is not a line
+fn lines_only(polylines: &[Vec<Vector3>]) -> Vec<&Vec<Vector3>> {
+    polylines.iter().filter(|polyline| polyline.len() >= 2).collect()
+}
+
+// Vertices of all polylines, then one line element per polyline
+// OBJ indices start at 1
+fn obj(polylines: &[Vec<Vector3>]) -> String {
+    let polylines = lines_only(polylines);
+    let mut text = String::new();
+    for p in polylines.iter().copied().flatten() {
+        text.push_str(&format!("v {} {} {}\n", p.x, p.y, p.z));
+    }
+    let mut first = 1;
+    for (i, polyline) in polylines.iter().enumerate() {
+        let indices: Vec<String> = (first..first + polyline.len())
+            .map(|index| index.to_string()).collect();
+        text.push_str(&format!("o polyline_{}\nl {}\n", i, indices.join(" ")));
+        first += polyline.len();
+    }
+    text
+}
+
+// Vertices of all polylines and an edge between consecutive points
+// of each, PLY indices start at 0
+fn ply(polylines: &[Vec<Vector3>]) -> String {
+    let polylines = lines_only(polylines);
+    let n_vertices: usize = polylines.iter().map(|p| p.len()).sum();
+    let n_edges: usize = polylines.iter().map(|p| p.len() - 1).sum();
+    let mut text = format!("ply\nformat ascii 1.0\n\
+        element vertex {}\nproperty double x\nproperty double y\nproperty double z\n\
+        element edge {}\nproperty int vertex1\nproperty int vertex2\nend_header\n",
+        n_vertices, n_edges);
+    for p in polylines.iter().copied().flatten() {
+        text.push_str(&format!("{} {} {}\n", p.x, p.y, p.z));
+    }
+    let mut first = 0;
+    for polyline in polylines {
+        for i in first..first + polyline.len() - 1 {
+            text.push_str(&format!("{} {}\n", i, i + 1));
+        }
+        first += polyline.len();
+    }
+    text
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrators::{RungeKutta4, State};
+    use crate::projectiles::Projectile;
+    use crate::standard_atmosphere::StandardAtmosphere;
+
+    fn same(a: Vector3, b: Vector3) -> bool {
+        a.x == b.x && a.y == b.y && a.z == b.z
+    }
+
+    #[test]
+    fn csv_round_trips_the_samples() {
+        let atm = StandardAtmosphere::new(0., Vector3::new(2., -1., 0.));
+        let mut ball = Projectile::new();
+        ball.mass = 0.05;
+        ball.radius = 0.02;
+        ball.drag_coefficient = 0.3;
+        ball.magnus_coefficient = 1e-5;
+        ball.spin = Vector3::new(0., -200., 30.);
+        ball.set_speed_theta_phi(40., 0.4, 0.1);
+        let result = ball.trajectory(&atm, &RungeKutta4, 0.05, 0., 10.);
+
+        let read = TrajectoryResult::from_csv_str(&result.to_csv()).unwrap();
+        assert_eq!(read.samples.len(), result.samples.len());
+        for (a, b) in result.samples.iter().zip(&read.samples) {
+            assert_eq!(a.time, b.time);
+            assert!(same(a.position, b.position));
+            assert!(same(a.velocity, b.velocity));
+            assert!(same(a.spin, b.spin));
+            assert!(b.mach.is_nan() && b.reynolds.is_nan());
+        }
+        assert!(read.landing.is_none() && read.events.is_empty());
+    }
+
+    #[test]
+    fn json_layout() {
+        let zero = Vector3::new(0., 0., 0.);
+        let sample = TrajectorySample::new(0.5, Vector3::new(1., 2., 3.),
+            Vector3::new(-4., 0.25, 6.), zero, Forces::new(zero, zero, zero),
+            0.1, f64::NAN);
+        let hit = EventHit::new(1.5, &State::new(Vector3::new(7., 0., 0.),
+            Vector3::new(1., 0., -1.), zero));
+        let result = TrajectoryResult::new(vec![sample], Some(1e-9), Some(hit),
+            None, vec![(2, hit)]);
+        let hit_json = "{\"time\":1.5,\"position\":[7,0,0],\
+            \"velocity\":[1,0,-1],\"spin\":[0,0,0]}";
+        assert_eq!(result.to_json(), format!("{{\"samples\":[\
+            {{\"time\":0.5,\"position\":[1,2,3],\"velocity\":[-4,0.25,6],\
+            \"spin\":[0,0,0],\"mach\":0.1,\"reynolds\":null}}],\
+            \"landing\":{hit},\"apex\":null,\
+            \"events\":[{{\"index\":2,\"hit\":{hit}}}],\
+            \"error_estimate\":0.000000001}}", hit = hit_json));
+    }
+
+    #[test]
+    fn csv_errors_report_the_line() {
+        let text = "# header\n\n1,2,3\n4,5,6\n";
+        match TrajectoryResult::from_csv_str(text) {
+            Err(ParseError::InvalidFormat{ line, .. }) => assert_eq!(line, 3),
+            _ => panic!("expected a format error"),
+        }
+    }
+
+    #[test]
+    fn single_points_are_not_written_as_lines() {
+        let point = vec![Vector3::new(1., 2., 3.)];
+        let line = vec![Vector3::new(0., 0., 0.), Vector3::new(1., 0., 0.)];
+        let polylines = [point, line];
+        assert_eq!(obj(&polylines),
+            "v 0 0 0\nv 1 0 0\no polyline_0\nl 1 2\n");
+        assert!(ply(&polylines).contains("element vertex 2\n"));
+        assert!(ply(&polylines).ends_with("end_header\n0 0 0\n1 0 0\n0 1\n"));
+    }
+}
@@ -0,0 +1,209 @@
+use crate::vector3::Vector3;
+use crate::constants;
+use crate::integrators::State;
+use crate::trajectory::TrajectoryResult;
+use crate::terrain::Terrain;
+
+// Bounces slower than this (m/s, along the normal) turn into a roll
+const MIN_BOUNCE_SPEED: f64 = 0.3;
+// Time step for the roll (s)
+const ROLL_STEP: f64 = 0.01;
+// Give up on balls that roll down a slope forever (s)
+const MAX_ROLL_TIME: f64 = 120.;
+
+// Ground the projectile interacts with after landing
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct Surface {
+    // Ratio of rebound to incoming normal speed
+    pub normal_restitution: f64,
+    // Ratio of rebound to incoming contact point tangential speed
+    // (negative, i.e. reversed) when the ball grips
+    pub tangential_restitution: f64,
+    // Coulomb friction coefficient limiting the tangential impulse
+    pub friction: f64,
+    // Rolling deceleration as a fraction of g
+    pub rolling_resistance: f64,
+    _private: (),
+}
+
+// Mass properties of the ball needed for the impact
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct Ball {
+    pub mass: f64,
+    pub radius: f64,
+    pub moment_of_inertia: f64,
+    _private: (),
+}
+
+// Full shot: the carry flight, any bounces and the roll out
+#[allow(clippy::manual_non_exhaustive)]
+pub struct ShotResult {
+    // Carry flight followed by one flight per bounce
+    pub flights: Vec<TrajectoryResult>,
+    // Positions while rolling, empty if the ball never rolled
+    pub roll: Vec<Vector3>,
+    _private: (),
+}
+
+impl Surface {
+    pub fn new(normal_restitution: f64, tangential_restitution: f64,
+        friction: f64, rolling_resistance: f64) -> Surface {
+        Surface{ normal_restitution, tangential_restitution,
+            friction, rolling_resistance, _private: () }
+    }
+
+    // Typical values for golf turf and a cart path
+    pub fn fairway() -> Surface {
+        Surface::new(0.40, 0.25, 0.40, 0.25)
+    }
+
+    pub fn rough() -> Surface {
+        Surface::new(0.25, 0.10, 0.60, 0.50)
+    }
+
+    // Roughly a stimp 10 green
+    pub fn green() -> Surface {
+        Surface::new(0.30, 0.30, 0.35, 0.055)
+    }
+
+    pub fn cart_path() -> Surface {
+        Surface::new(0.70, 0.20, 0.50, 0.02)
+    }
+
+    // Rigid sphere impact on a surface with the given unit normal
+    // The normal velocity is reversed and scaled by normal_restitution,
+    // the tangential impulse tries to reverse the contact point velocity
+    // (scaled by tangential_restitution) but is capped by friction,
+    // which is what reverses backspin on steep landings
+    pub fn bounce(&self, ball: &Ball, state: &State, normal: Vector3) -> State {
+        let v_normal = state.velocity.dot(normal);
+        let v_tangent = state.velocity - normal * v_normal;
+        let contact = normal * -ball.radius;
+
+        // Normal impulse
+        let impulse_normal = ball.mass * (1. + self.normal_restitution)
+            * v_normal.abs();
+
+        // Contact point tangential velocity
+        let slip = v_tangent + Vector3::cross_prod(&state.spin, &contact);
+        let slip_speed = slip.mag();
+        let mut velocity = v_tangent + normal * (self.normal_restitution
+            * v_normal.abs());
+        let mut spin = state.spin;
+        if slip_speed > 0. {
+            // Effective mass for tangential impulses at the contact point
+            let inertia_ratio = ball.moment_of_inertia
+                / (ball.mass * ball.radius.powi(2));
+            let effective_mass = ball.mass * inertia_ratio / (1. + inertia_ratio);
+            let impulse_grip = effective_mass
+                * (1. + self.tangential_restitution) * slip_speed;
+            let impulse = impulse_grip.min(self.friction * impulse_normal);
+
+            let slip_dir = slip.normalize();
+            velocity = velocity - slip_dir * (impulse / ball.mass);
+            spin = spin + Vector3::cross_prod(&contact, &(slip_dir * -impulse))
+                * (1. / ball.moment_of_inertia);
+        }
+
+        State::new(state.position, velocity, spin)
+    }
+
+    // Will the ball leave the ground again after this bounce
+    pub fn is_bounce(&self, state: &State, normal: Vector3) -> bool {
+        state.velocity.dot(normal) > MIN_BOUNCE_SPEED
+    }
+
+    // Roll along the terrain until the ball comes to rest,
+    // returns the positions along the way
+    // Gravity along the slope drives the ball, rolling resistance opposes
+    // its motion, it stays put once the slope can not overcome the resistance
+    pub fn roll(&self, state: &State, terrain: &dyn Terrain) -> Vec<Vector3> {
+        let gravity = Vector3::new(0., 0., -constants::G);
+        let mut position = state.position;
+        let mut velocity = state.velocity;
+        let mut positions = vec![position];
+
+        let mut t = 0.;
+        while t < MAX_ROLL_TIME {
+            let normal = terrain.normal(position.x, position.y);
+            velocity = velocity - normal * velocity.dot(normal);
+            let downhill = gravity - normal * gravity.dot(normal);
+            let resistance = self.rolling_resistance * constants::G * normal.z;
+            let speed = velocity.mag();
+
+            if speed == 0. && downhill.mag() <= resistance {
+                break;
+            }
+            let accel = if speed > 0. {
+                downhill - velocity.normalize() * resistance
+            } else {
+                downhill.normalize() * (downhill.mag() - resistance)
+            };
+
+            let mut dt = ROLL_STEP;
+            let mut new_velocity = velocity + accel * dt;
+            if speed > 0. && new_velocity.dot(velocity) <= 0. {
+                // Comes to a stop within this step
+                dt = speed / -accel.dot(velocity.normalize());
+                new_velocity = Vector3::new(0., 0., 0.);
+            }
+            position = position + (velocity + new_velocity) * (dt / 2.);
+            position.z = terrain.height(position.x, position.y);
+            velocity = new_velocity;
+            positions.push(position);
+            t += dt;
+        }
+        positions
+    }
+}
+
+impl Ball {
+    pub fn new(mass: f64, radius: f64, moment_of_inertia: f64) -> Ball {
+        Ball{ mass, radius, moment_of_inertia, _private: () }
+    }
+}
+
+impl ShotResult {
+    pub fn new(flights: Vec<TrajectoryResult>, roll: Vec<Vector3>)
+        -> ShotResult {
+        ShotResult{ flights, roll, _private: () }
+    }
+
+    pub fn carry(&self) -> f64 {
+        self.flights[0].carry()
+    }
+
+    pub fn bounce_count(&self) -> usize {
+        self.flights.len() - 1
+    }
+
+    pub fn start_position(&self) -> Vector3 {
+        self.flights[0].first().position
+    }
+
+    // Resting point, or wherever the last flight ended
+    pub fn final_position(&self) -> Vector3 {
+        match self.roll.last() {
+            Some(position) => *position,
+            None => self.flights[self.flights.len() - 1].last().position,
+        }
+    }
+
+    pub fn roll_distance(&self) -> f64 {
+        match (self.roll.first(), self.roll.last()) {
+            (Some(start), Some(end)) => horizontal_distance(*start, *end),
+            _ => 0.,
+        }
+    }
+
+    // Horizontal distance from launch to where the ball stopped
+    pub fn total_distance(&self) -> f64 {
+        horizontal_distance(self.start_position(), self.final_position())
+    }
+}
+
+fn horizontal_distance(a: Vector3, b: Vector3) -> f64 {
+    ((b.x - a.x).powi(2) + (b.y - a.y).powi(2)).sqrt()
+}
@@ -0,0 +1,292 @@
+use std::ops::{Add, Mul};
+
+use crate::vector3::Vector3;
+
+// State of a spinning point mass that the integrators advance in time
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct State {
+    pub position: Vector3,
+    pub velocity: Vector3,
+    pub spin: Vector3,
+    _private: (),
+}
+
+// Time derivative of a State
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct Derivative {
+    pub velocity: Vector3,
+    pub acceleration: Vector3,
+    pub spin_rate: Vector3,
+    _private: (),
+}
+
+// Outcome of a single integration step
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct Step {
+    // State at t + dt
+    pub state: State,
+    // Step size that was actually taken
+    pub dt: f64,
+    // Step size suggested for the next step
+    pub dt_next: f64,
+    // Local error estimate, only for methods that provide one
+    pub error: Option<f64>,
+    _private: (),
+}
+
+impl State {
+    pub fn new(position: Vector3, velocity: Vector3, spin: Vector3) -> State {
+        State{ position, velocity, spin, _private: () }
+    }
+
+    // Euler update y + h * dy/dt
+    pub fn advance(&self, derivative: &Derivative, h: f64) -> State {
+        State::new(self.position + derivative.velocity * h,
+            self.velocity + derivative.acceleration * h,
+            self.spin + derivative.spin_rate * h)
+    }
+
+    fn zero() -> State {
+        let zero = Vector3::new(0., 0., 0.);
+        State::new(zero, zero, zero)
+    }
+
+    // Largest absolute value over all components
+    fn max_abs(&self) -> f64 {
+        let p = self.position;
+        let v = self.velocity;
+        let w = self.spin;
+        [p.x, p.y, p.z, v.x, v.y, v.z, w.x, w.y, w.z].iter()
+            .fold(0., |acc: f64, c| acc.max(c.abs()))
+    }
+
+    // Largest error component scaled by atol + rtol * |y|
+    fn scaled_error(&self, new: &State, error: &State,
+        abs_tol: f64, rel_tol: f64) -> f64 {
+        let vectors = [(self.position, new.position, error.position),
+                       (self.velocity, new.velocity, error.velocity),
+                       (self.spin, new.spin, error.spin)];
+
+        let mut max_err: f64 = 0.;
+        for (y, y_new, err) in vectors {
+            let comps = [(y.x, y_new.x, err.x),
+                         (y.y, y_new.y, err.y),
+                         (y.z, y_new.z, err.z)];
+            for (c, c_new, c_err) in comps {
+                let scale = abs_tol + rel_tol * c.abs().max(c_new.abs());
+                max_err = max_err.max(c_err.abs() / scale);
+            }
+        }
+        max_err
+    }
+}
+
+impl Derivative {
+    pub fn new(velocity: Vector3, acceleration: Vector3, spin_rate: Vector3)
+        -> Derivative {
+        Derivative{ velocity, acceleration, spin_rate, _private: () }
+    }
+}
+
+impl Add<Derivative> for Derivative {
+    type Output = Derivative;
+
+    fn add(self, _rhs: Derivative) -> Derivative {
+        Derivative::new(self.velocity + _rhs.velocity,
+            self.acceleration + _rhs.acceleration,
+            self.spin_rate + _rhs.spin_rate)
+    }
+}
+
+impl Mul<f64> for Derivative {
+    type Output = Derivative;
+
+    fn mul(self, _rhs: f64) -> Derivative {
+        Derivative::new(self.velocity * _rhs, self.acceleration * _rhs,
+            self.spin_rate * _rhs)
+    }
+}
+
+impl Step {
+    pub fn new(state: State, dt: f64, dt_next: f64, error: Option<f64>)
+        -> Step {
+        Step{ state, dt, dt_next, error, _private: () }
+    }
+}
+
+// Smallest step the trajectories take (s), so that a collapsing
+// adaptive step cannot stall them
+pub const MIN_STEP: f64 = 1e-9;
+
+// Right hand side of the equations of motion, dy/dt = f(t, y)
+pub type DerivativeFn<'a> = dyn Fn(f64, &State) -> Derivative + 'a;
+
+pub trait Integrator: Send + Sync {
+    // Advance state from t by (at most) dt
+    fn step(&self, f: &DerivativeFn, t: f64, state: &State, dt: f64) -> Step;
+}
+
+
+/********************
+*********************
+Fixed Step Integrators
+*********************
+********************/
+
+// Forward (explicit) Euler, first order
+#[derive(Copy, Clone, Default)]
+pub struct ExplicitEuler;
+
+impl Integrator for ExplicitEuler {
+    fn step(&self, f: &DerivativeFn, t: f64, state: &State, dt: f64) -> Step {
+        let k1 = f(t, state);
+        Step::new(state.advance(&k1, dt), dt, dt, None)
+    }
+}
+
+// Semi-implicit (symplectic) Euler, velocity is updated first
+// and the new velocity is used for the position update
+#[derive(Copy, Clone, Default)]
+pub struct SemiImplicitEuler;
+
+impl Integrator for SemiImplicitEuler {
+    fn step(&self, f: &DerivativeFn, t: f64, state: &State, dt: f64) -> Step {
+        let k1 = f(t, state);
+        let velocity = state.velocity + k1.acceleration * dt;
+        let position = state.position + velocity * dt;
+        let spin = state.spin + k1.spin_rate * dt;
+        Step::new(State::new(position, velocity, spin), dt, dt, None)
+    }
+}
+
+// Velocity Verlet, second order
+// Velocity dependent forces (drag, Magnus) are evaluated with
+// an Euler predicted velocity and spin at the end of the step
+#[derive(Copy, Clone, Default)]
+pub struct VelocityVerlet;
+
+impl Integrator for VelocityVerlet {
+    fn step(&self, f: &DerivativeFn, t: f64, state: &State, dt: f64) -> Step {
+        let k1 = f(t, state);
+        let accel = k1.acceleration;
+        let position = state.position + state.velocity * dt
+            + accel * (dt.powi(2) / 2.);
+        let predicted = State::new(position, state.velocity + accel * dt,
+            state.spin + k1.spin_rate * dt);
+        let k2 = f(t + dt, &predicted);
+        let velocity = state.velocity + (accel + k2.acceleration) * (dt / 2.);
+        let spin = state.spin + (k1.spin_rate + k2.spin_rate) * (dt / 2.);
+        Step::new(State::new(position, velocity, spin), dt, dt, None)
+    }
+}
+
+// Classic fourth order Runge-Kutta
+#[derive(Copy, Clone, Default)]
+pub struct RungeKutta4;
+
+impl Integrator for RungeKutta4 {
+    fn step(&self, f: &DerivativeFn, t: f64, state: &State, dt: f64) -> Step {
+        let k1 = f(t, state);
+        let k2 = f(t + dt / 2., &state.advance(&k1, dt / 2.));
+        let k3 = f(t + dt / 2., &state.advance(&k2, dt / 2.));
+        let k4 = f(t + dt, &state.advance(&k3, dt));
+        let slope = (k1 + k2 * 2. + k3 * 2. + k4) * (1. / 6.);
+        Step::new(state.advance(&slope, dt), dt, dt, None)
+    }
+}
+
+
+/*****************
+******************
+Adaptive Integrator
+******************
+*****************/
+
+// Dormand-Prince embedded Runge-Kutta 5(4)
+// Step size is controlled so that every component of the local error
+// stays below abs_tol + rel_tol * |y|
+// https://en.wikipedia.org/wiki/Dormand%E2%80%93Prince_method
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct DormandPrince45 {
+    pub abs_tol: f64,
+    pub rel_tol: f64,
+    pub min_step: f64,
+    pub max_step: f64,
+    _private: (),
+}
+
+impl DormandPrince45 {
+    pub fn new(abs_tol: f64, rel_tol: f64) -> DormandPrince45 {
+        DormandPrince45{ abs_tol, rel_tol,
+            min_step: 1e-6, max_step: 1., _private: () }
+    }
+
+    // Single trial step, returns the fifth order solution
+    // and the difference to the embedded fourth order solution
+    fn trial(f: &DerivativeFn, t: f64, state: &State, h: f64)
+        -> (State, State) {
+        let k1 = f(t, state);
+        let k2 = f(t + h / 5., &state.advance(&(k1 * (1. / 5.)), h));
+        let k3 = f(t + 3. * h / 10., &state.advance(
+            &(k1 * (3. / 40.) + k2 * (9. / 40.)), h));
+        let k4 = f(t + 4. * h / 5., &state.advance(
+            &(k1 * (44. / 45.) + k2 * (-56. / 15.) + k3 * (32. / 9.)), h));
+        let k5 = f(t + 8. * h / 9., &state.advance(
+            &(k1 * (19372. / 6561.) + k2 * (-25360. / 2187.)
+                + k3 * (64448. / 6561.) + k4 * (-212. / 729.)), h));
+        let k6 = f(t + h, &state.advance(
+            &(k1 * (9017. / 3168.) + k2 * (-355. / 33.)
+                + k3 * (46732. / 5247.) + k4 * (49. / 176.)
+                + k5 * (-5103. / 18656.)), h));
+        let slope = k1 * (35. / 384.) + k3 * (500. / 1113.)
+            + k4 * (125. / 192.) + k5 * (-2187. / 6784.) + k6 * (11. / 84.);
+        let new_state = state.advance(&slope, h);
+        let k7 = f(t + h, &new_state);
+
+        // Fifth minus fourth order weights
+        let err_slope = k1 * (71. / 57600.) + k3 * (-71. / 16695.)
+            + k4 * (71. / 1920.) + k5 * (-17253. / 339200.)
+            + k6 * (22. / 525.) + k7 * (-1. / 40.);
+        (new_state, State::zero().advance(&err_slope, h))
+    }
+}
+
+impl Default for DormandPrince45 {
+    fn default() -> Self {
+        DormandPrince45::new(1e-6, 1e-6)
+    }
+}
+
+impl Integrator for DormandPrince45 {
+    fn step(&self, f: &DerivativeFn, t: f64, state: &State, dt: f64) -> Step {
+        // Standard safety factor and growth limits
+        let safety = 0.9;
+        let min_factor = 0.2;
+        let max_factor = 5.;
+
+        let min_step = self.min_step.max(MIN_STEP);
+        let max_step = self.max_step.max(min_step);
+        let mut h = dt.clamp(min_step, max_step);
+        loop {
+            let (new_state, error) = DormandPrince45::trial(f, t, state, h);
+            let err_norm = state.scaled_error(&new_state, &error,
+                self.abs_tol, self.rel_tol);
+            let factor = if err_norm == 0. {
+                max_factor
+            } else {
+                (safety * err_norm.powf(-0.2)).clamp(min_factor, max_factor)
+            };
+
+            if err_norm <= 1. || h <= min_step {
+                let dt_next = (h * factor).clamp(min_step, max_step);
+                return Step::new(new_state, h, dt_next,
+                    Some(error.max_abs()));
+            }
+            h = (h * factor).max(min_step);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+// Launch conditions that maximize carry or total distance
+//
+// The search runs over launch angle, spin rate and spin axis tilt
+// (see Projectile::set_spin_rate_axis) at a fixed ball speed, keeping
+// the projectile's current position and launch azimuth
+
+use crate::atmosphere::AtmosphereModel;
+use crate::integrators::Integrator;
+use crate::projectiles::Projectile;
+use crate::ground::Surface;
+use crate::optimization;
+
+// Longest flight considered (s)
+const MAX_FLIGHT_TIME: f64 = 60.;
+// Search runs on parameters scaled to [0, 1] over their bounds
+const INITIAL_STEP: f64 = 0.25;
+const DISTANCE_TOL: f64 = 1e-4;
+const MAX_ITER: usize = 200;
+// Finite difference step for the sensitivities, fraction of the bounds
+const DIFF_STEP: f64 = 0.02;
+
+// What to maximize
+#[derive(Copy, Clone)]
+pub enum Objective {
+    Carry,
+    // Carry plus bounce and roll on a flat surface at launch height
+    TotalDistance(Surface),
+}
+
+// Search ranges, (min, max) for each parameter
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct LaunchBounds {
+    pub theta: (f64, f64),
+    pub spin_rate: (f64, f64),
+    pub spin_axis: (f64, f64),
+    _private: (),
+}
+
+// Optimal launch and how the distance changes around it
+// Parameter arrays are ordered theta, spin_rate, spin_axis
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct LaunchWindow {
+    pub theta: f64,
+    pub spin_rate: f64,
+    pub spin_axis: f64,
+    pub distance: f64,
+    // First and second derivatives of the distance at the optimum
+    pub gradient: [f64; 3],
+    pub curvature: [f64; 3],
+    pub bounds: LaunchBounds,
+    _private: (),
+}
+
+impl LaunchBounds {
+    pub fn new(theta: (f64, f64), spin_rate: (f64, f64),
+        spin_axis: (f64, f64)) -> LaunchBounds {
+        LaunchBounds{ theta, spin_rate, spin_axis, _private: () }
+    }
+
+    fn as_array(&self) -> [(f64, f64); 3] {
+        [self.theta, self.spin_rate, self.spin_axis]
+    }
+
+    // Map [0, 1] coordinates onto the bounds
+    fn scale(&self, u: &[f64]) -> [f64; 3] {
+        let bounds = self.as_array();
+        [0, 1, 2].map(|i| bounds[i].0 + u[i] * (bounds[i].1 - bounds[i].0))
+    }
+}
+
+impl LaunchWindow {
+    pub fn parameters(&self) -> [f64; 3] {
+        [self.theta, self.spin_rate, self.spin_axis]
+    }
+
+    // Range of each parameter, the others held at the optimum, that keeps
+    // the distance within loss of the optimum according to a quadratic
+    // model of the distance, limited to the search bounds
+    pub fn window(&self, loss: f64) -> [(f64, f64); 3] {
+        let bounds = self.bounds.as_array();
+        let params = self.parameters();
+        [0, 1, 2].map(|i| {
+            let (g, c) = (self.gradient[i], self.curvature[i]);
+            // Solve g * d + c * d^2 / 2 = -loss on either side
+            let (below, above) = if c < 0. {
+                let disc = (g.powi(2) - 2. * c * loss).sqrt();
+                ((-g + disc) / c, (-g - disc) / c)
+            } else if g > 0. {
+                (-loss / g, f64::INFINITY)
+            } else if g < 0. {
+                (f64::NEG_INFINITY, -loss / g)
+            } else {
+                (f64::NEG_INFINITY, f64::INFINITY)
+            };
+            ((params[i] + below).max(bounds[i].0),
+                (params[i] + above).min(bounds[i].1))
+        })
+    }
+}
+
+impl Projectile {
+    // Distance for one launch, see Objective
+    pub fn launch_distance(
+        &self, atm: &dyn AtmosphereModel, integrator: &dyn Integrator,
+        t_step: f64, launch: (f64, f64, f64, f64), objective: &Objective) -> f64 {
+
+        let (speed, theta, spin_rate, spin_axis) = launch;
+        let mut shot = self.clone();
+        shot.set_speed_theta_phi(speed, theta, self.get_phi());
+        shot.set_spin_rate_axis(spin_rate, spin_axis);
+        let end_height = self.position.z;
+        match objective {
+            Objective::Carry => shot.trajectory(atm, integrator, t_step,
+                end_height, MAX_FLIGHT_TIME).carry(),
+            Objective::TotalDistance(surface) => shot.shot(atm, integrator,
+                t_step, surface, end_height, MAX_FLIGHT_TIME).total_distance(),
+        }
+    }
+
+    // Nelder-Mead search for the launch angle, spin rate and spin axis
+    // that maximize the objective at the given ball speed
+    pub fn optimize_launch(
+        &self, atm: &dyn AtmosphereModel, integrator: &dyn Integrator,
+        t_step: f64, speed: f64, bounds: &LaunchBounds, objective: &Objective)
+        -> LaunchWindow {
+
+        let distance = |u: &[f64]| {
+            let [theta, spin_rate, spin_axis] = bounds.scale(u);
+            self.launch_distance(atm, integrator, t_step,
+                (speed, theta, spin_rate, spin_axis), objective)
+        };
+
+        let unit_box = [(0., 1.); 3];
+        let (best, neg_distance) = optimization::nelder_mead(
+            |u| -distance(u), &[0.5; 3], &[INITIAL_STEP; 3], &unit_box,
+            DISTANCE_TOL, MAX_ITER);
+        let best_distance = -neg_distance;
+
+        // Quadratic through three points around the optimum in scaled
+        // coordinates, shifted inwards when the optimum is on a bound
+        let ranges = bounds.as_array().map(|(lo, hi)| hi - lo);
+        let mut gradient = [0.; 3];
+        let mut curvature = [0.; 3];
+        for i in 0..3 {
+            if ranges[i] == 0. {
+                continue;
+            }
+            let center = best[i].clamp(DIFF_STEP, 1. - DIFF_STEP);
+            let at = |ui: f64| {
+                let mut u = best.clone();
+                u[i] = ui;
+                if ui == best[i] { best_distance } else { distance(&u) }
+            };
+            let (d_lower, d_center, d_upper) = (at(center - DIFF_STEP),
+                at(center), at(center + DIFF_STEP));
+            let second = (d_lower - 2. * d_center + d_upper) / DIFF_STEP.powi(2);
+            let first = (d_upper - d_lower) / (2. * DIFF_STEP)
+                + second * (best[i] - center);
+            gradient[i] = first / ranges[i];
+            curvature[i] = second / ranges[i].powi(2);
+        }
+
+        let [theta, spin_rate, spin_axis] = bounds.scale(&best);
+        LaunchWindow{ theta, spin_rate, spin_axis, distance: best_distance,
+            gradient, curvature, bounds: *bounds, _private: () }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants;
+    use crate::standard_atmosphere::StandardAtmosphere;
+    use crate::integrators::RungeKutta4;
+    use crate::vector3::Vector3;
+
+    // Without drag or Magnus force the carry is v^2 sin(2 theta) / g,
+    // largest at 45 degrees whatever the spin
+    #[test]
+    fn drag_free_carry_is_best_at_45_degrees() {
+        let atm = StandardAtmosphere::new(0., Vector3::new(0., 0., 0.));
+        let mut ball = Projectile::new();
+        ball.mass = 0.05;
+        ball.radius = 0.02;
+        let speed = 20.;
+        let bounds = LaunchBounds::new((0.3, 1.2), (0., 300.), (-0.5, 0.5));
+        let optimum = ball.optimize_launch(&atm, &RungeKutta4, 0.01, speed,
+            &bounds, &Objective::Carry);
+
+        let g = constants::G;
+        let best = speed.powi(2) / g;
+        let quarter_pi = std::f64::consts::FRAC_PI_4;
+        assert!((optimum.theta - quarter_pi).abs() < 0.01);
+        assert!((optimum.distance - best).abs() < 1e-3);
+        // d''(theta) = -4 v^2 / g, the spin does nothing
+        assert!((optimum.curvature[0] / (-4. * best) - 1.).abs() < 0.01);
+        assert_eq!(optimum.gradient[1], 0.);
+        assert_eq!(optimum.curvature[2], 0.);
+
+        // Losing 0.5 m is about sqrt(0.5 / (2 v^2 / g)) either side of
+        // the optimum, the spin ranges are the whole bounds
+        let [theta, spin_rate, spin_axis] = optimum.window(0.5);
+        let half_width = (0.5 / (2. * best)).sqrt();
+        assert!((theta.0 - (quarter_pi - half_width)).abs() < 0.01);
+        assert!((theta.1 - (quarter_pi + half_width)).abs() < 0.01);
+        assert_eq!(spin_rate, bounds.spin_rate);
+        assert_eq!(spin_axis, bounds.spin_axis);
+    }
+}
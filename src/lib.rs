@@ -5,3 +5,29 @@ pub mod pauli_matrices;
 pub mod projectiles;
 pub mod vector3;
 pub mod atmosphere;
+pub mod integrators;
+pub mod trajectory;
+pub mod events;
+pub mod root_finding;
+pub mod aerodynamics;
+pub mod spin_decay;
+pub mod ground;
+pub mod terrain;
+pub mod parse;
+pub mod targeting;
+pub mod optimization;
+pub mod launch_window;
+pub mod random;
+pub mod dispersion;
+pub mod standard_atmosphere;
+pub mod wind;
+pub mod turbulence;
+pub mod psychrometrics;
+pub mod sounding;
+pub mod earth_frame;
+pub mod ballistics;
+pub mod projectile_builder;
+pub mod shot_parameters;
+pub mod calibration;
+pub mod export;
+pub mod plot;
@@ -0,0 +1,305 @@
+// Derivative free minimization and nonlinear least squares
+
+// Standard Nelder-Mead coefficients
+const REFLECTION: f64 = 1.;
+const EXPANSION: f64 = 2.;
+const CONTRACTION: f64 = 0.5;
+const SHRINK: f64 = 0.5;
+
+// Nelder-Mead simplex minimization of f inside the box given by bounds,
+// points outside the box are projected back onto it
+// step is the initial simplex size along each axis, the search stops once
+// the spread of f over the simplex drops below tol or after max_iter
+// Returns the best point and its value
+// https://en.wikipedia.org/wiki/Nelder%E2%80%93Mead_method
+pub fn nelder_mead<F: Fn(&[f64]) -> f64>(
+    f: F, x0: &[f64], step: &[f64], bounds: &[(f64, f64)],
+    tol: f64, max_iter: usize) -> (Vec<f64>, f64) {
+
+    let n = x0.len();
+    let clamp = |x: Vec<f64>| -> Vec<f64> {
+        x.iter().zip(bounds).map(|(xi, (lo, hi))| xi.clamp(*lo, *hi)).collect()
+    };
+    let eval = |x: Vec<f64>| -> (Vec<f64>, f64) {
+        let x = clamp(x);
+        let fx = f(&x);
+        (x, fx)
+    };
+
+    // Initial simplex, stepping backwards where a step would leave the box
+    let mut simplex: Vec<(Vec<f64>, f64)> = vec![eval(x0.to_vec())];
+    for i in 0..n {
+        let mut x = x0.to_vec();
+        x[i] = if x[i] + step[i] <= bounds[i].1 {
+            x[i] + step[i]
+        } else {
+            x[i] - step[i]
+        };
+        simplex.push(eval(x));
+    }
+
+    // Point along the line from the centroid through the worst point
+    let along = |centroid: &[f64], worst: &[f64], coef: f64| -> Vec<f64> {
+        centroid.iter().zip(worst)
+            .map(|(c, w)| c + coef * (c - w)).collect()
+    };
+
+    for _ in 0..max_iter {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let best = simplex[0].1;
+        let worst = simplex[n].1;
+        if (worst - best).abs() <= tol {
+            break;
+        }
+
+        let centroid: Vec<f64> = (0..n).map(|j| {
+            simplex[..n].iter().map(|(x, _)| x[j]).sum::<f64>() / n as f64
+        }).collect();
+        let worst_x = simplex[n].0.clone();
+
+        let reflected = eval(along(&centroid, &worst_x, REFLECTION));
+        if reflected.1 < best {
+            let expanded = eval(along(&centroid, &worst_x, EXPANSION));
+            simplex[n] = if expanded.1 < reflected.1 { expanded } else { reflected };
+        } else if reflected.1 < simplex[n - 1].1 {
+            simplex[n] = reflected;
+        } else {
+            let contracted = if reflected.1 < worst {
+                eval(along(&centroid, &worst_x, CONTRACTION))
+            } else {
+                eval(along(&centroid, &worst_x, -CONTRACTION))
+            };
+            if contracted.1 < worst.min(reflected.1) {
+                simplex[n] = contracted;
+            } else {
+                // Shrink towards the best point
+                let best_x = simplex[0].0.clone();
+                for vertex in simplex.iter_mut().skip(1) {
+                    let x = best_x.iter().zip(&vertex.0)
+                        .map(|(b, v)| b + SHRINK * (v - b)).collect();
+                    *vertex = eval(x);
+                }
+            }
+        }
+    }
+
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    simplex.swap_remove(0)
+}
+
+
+/**********************
+***********************
+Nonlinear Least Squares
+***********************
+**********************/
+
+// Levenberg-Marquardt damping, scaled down after a successful step and up
+// after a failed one, the search gives up once it exceeds MAX_DAMPING
+const INITIAL_DAMPING: f64 = 1e-3;
+const DAMPING_DOWN: f64 = 0.1;
+const DAMPING_UP: f64 = 10.;
+const MAX_DAMPING: f64 = 1e10;
+// Forward difference step for the Jacobian, relative to each parameter
+// (absolute for parameters at zero)
+const JACOBIAN_STEP: f64 = 1e-4;
+
+// Result of a least squares fit
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Clone)]
+pub struct LeastSquares {
+    pub parameters: Vec<f64>,
+    pub residuals: Vec<f64>,
+    // Sum of squared residuals
+    pub cost: f64,
+    // Square roots of the diagonal of the covariance s^2 (J^T J)^-1,
+    // s^2 being the cost per degree of freedom, NaN when undetermined
+    pub standard_errors: Vec<f64>,
+    pub iterations: usize,
+    pub converged: bool,
+    _private: (),
+}
+
+// Levenberg-Marquardt minimization of the sum of squared residuals r(x)
+// r returns None where it cannot be evaluated, steps there are rejected
+// The search stops once a step lowers the cost by less than tol relative
+// to it, when no step lowers the cost, or after max_iter, None if r
+// cannot be evaluated at x0. converged is false if the search stopped
+// because trial steps could not be evaluated or solved for
+// https://en.wikipedia.org/wiki/Levenberg%E2%80%93Marquardt_algorithm
+pub fn levenberg_marquardt<F: Fn(&[f64]) -> Option<Vec<f64>>>(
+    r: F, x0: &[f64], tol: f64, max_iter: usize) -> Option<LeastSquares> {
+
+    let n = x0.len();
+    let sum_sq = |res: &[f64]| res.iter().map(|ri| ri.powi(2)).sum::<f64>();
+    let jacobian = |x: &[f64], res: &[f64]| -> Option<Vec<Vec<f64>>> {
+        (0..n).map(|j| {
+            let h = if x[j] != 0. { JACOBIAN_STEP * x[j].abs() } else { JACOBIAN_STEP };
+            let mut xh = x.to_vec();
+            xh[j] += h;
+            let res_h = r(&xh)?;
+            Some(res_h.iter().zip(res).map(|(rh, ri)| (rh - ri) / h).collect())
+        }).collect()
+    };
+    // J^T J and J^T r from the columns of J
+    let normal_equations = |columns: &[Vec<f64>], res: &[f64]| {
+        let jtj: Vec<Vec<f64>> = columns.iter().map(|ci| {
+            columns.iter().map(|cj| dot(ci, cj)).collect()
+        }).collect();
+        let jtr: Vec<f64> = columns.iter().map(|ci| dot(ci, res)).collect();
+        (jtj, jtr)
+    };
+
+    let mut x = x0.to_vec();
+    let mut res = r(&x)?;
+    let mut cost = sum_sq(&res);
+    let mut damping = INITIAL_DAMPING;
+    let mut converged = false;
+    let mut stalled = false;
+    let mut iterations = 0;
+
+    while iterations < max_iter && !converged && !stalled {
+        iterations += 1;
+        let Some(columns) = jacobian(&x, &res) else { break };
+        let (jtj, jtr) = normal_equations(&columns, &res);
+
+        // Raise the damping until a step lowers the cost
+        let mut failed_trial = false;
+        loop {
+            let mut a = jtj.clone();
+            for (i, row) in a.iter_mut().enumerate() {
+                row[i] += damping * jtj[i][i].max(f64::MIN_POSITIVE);
+            }
+            let minus_jtr: Vec<f64> = jtr.iter().map(|v| -v).collect();
+            let trial = solve(a, minus_jtr).map(|step| {
+                x.iter().zip(&step).map(|(xi, si)| xi + si).collect::<Vec<f64>>()
+            });
+            let trial_res = trial.as_ref().and_then(|xt| r(xt));
+            match (trial, trial_res) {
+                (Some(xt), Some(rt)) => {
+                    let trial_cost = sum_sq(&rt);
+                    if trial_cost < cost {
+                        converged = cost - trial_cost <= tol * cost;
+                        x = xt;
+                        res = rt;
+                        cost = trial_cost;
+                        damping *= DAMPING_DOWN;
+                        break;
+                    }
+                },
+                _ => failed_trial = true,
+            }
+            damping *= DAMPING_UP;
+            if damping > MAX_DAMPING {
+                // If every trial was evaluated and none lowers the cost,
+                // x is a minimum to within the accuracy of r
+                converged = !failed_trial;
+                stalled = true;
+                break;
+            }
+        }
+    }
+
+    // Covariance from the Jacobian at the solution
+    let dof = res.len() as f64 - n as f64;
+    let standard_errors = match jacobian(&x, &res) {
+        Some(columns) if dof > 0. => {
+            let (jtj, _) = normal_equations(&columns, &res);
+            (0..n).map(|i| {
+                let mut unit = vec![0.; n];
+                unit[i] = 1.;
+                solve(jtj.clone(), unit)
+                    .map_or(f64::NAN, |column| (column[i] * cost / dof).sqrt())
+            }).collect()
+        },
+        _ => vec![f64::NAN; n],
+    };
+
+    Some(LeastSquares{ parameters: x, residuals: res, cost, standard_errors,
+        iterations, converged, _private: () })
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(ai, bi)| ai * bi).sum()
+}
+
+// Gaussian elimination with partial pivoting, None if a is singular
+fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    for k in 0..n {
+        let pivot = (k..n).max_by(|&i, &j| a[i][k].abs().total_cmp(&a[j][k].abs()))?;
+        if a[pivot][k] == 0. || !a[pivot][k].is_finite() {
+            return None;
+        }
+        a.swap(k, pivot);
+        b.swap(k, pivot);
+        for i in k + 1..n {
+            let factor = a[i][k] / a[k][k];
+            let (above, below) = a.split_at_mut(i);
+            for (aij, akj) in below[0][k..].iter_mut().zip(&above[k][k..]) {
+                *aij -= factor * akj;
+            }
+            b[i] -= factor * b[k];
+        }
+    }
+    let mut x = vec![0.; n];
+    for k in (0..n).rev() {
+        let sum: f64 = (k + 1..n).map(|j| a[k][j] * x[j]).sum();
+        x[k] = (b[k] - sum) / a[k][k];
+    }
+    Some(x)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (x - 1)^2 + 10 (y + 2)^2 + (x - 1)(y + 2), minimum 3 at (1, -2)
+    fn quadratic(p: &[f64]) -> f64 {
+        let (dx, dy) = (p[0] - 1., p[1] + 2.);
+        dx.powi(2) + 10. * dy.powi(2) + dx * dy + 3.
+    }
+
+    #[test]
+    fn nelder_mead_finds_a_quadratic_minimum() {
+        let bounds = [(-10., 10.); 2];
+        let (best, value) = nelder_mead(quadratic, &[5., 5.], &[1., 1.],
+            &bounds, 1e-14, 1000);
+        assert!((best[0] - 1.).abs() < 1e-5);
+        assert!((best[1] + 2.).abs() < 1e-5);
+        assert!((value - 3.).abs() < 1e-10);
+    }
+
+    #[test]
+    fn nelder_mead_stops_on_the_bounds() {
+        // Minimum outside the box, the best point in it is on the
+        // y = 0 edge where the minimum over x is at x = 0
+        let bounds = [(-10., 10.), (0., 10.)];
+        let (best, value) = nelder_mead(quadratic, &[5., 5.], &[1., 1.],
+            &bounds, 1e-14, 1000);
+        assert!(best[1].abs() < 1e-12);
+        assert!(best[0].abs() < 1e-5);
+        assert!((value - quadratic(&[0., 0.])).abs() < 1e-10);
+    }
+
+    #[test]
+    fn fits_a_line() {
+        let data = [(0., 1.), (1., 3.), (2., 5.), (3., 7.)];
+        let r = |p: &[f64]| Some(data.iter().map(|(x, y)| p[0] + p[1] * x - y)
+            .collect::<Vec<f64>>());
+        let fit = levenberg_marquardt(r, &[0., 0.], 1e-12, 100).unwrap();
+        assert!(fit.converged);
+        assert!((fit.parameters[0] - 1.).abs() < 1e-6);
+        assert!((fit.parameters[1] - 2.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn stall_on_failed_trials_is_not_converged() {
+        // Every step towards the minimum at 0 leaves the domain
+        let r = |p: &[f64]| if p[0] >= 1. { Some(vec![p[0]]) } else { None };
+        let fit = levenberg_marquardt(r, &[1.], 1e-12, 100).unwrap();
+        assert!(!fit.converged);
+        assert_eq!(fit.parameters, vec![1.]);
+    }
+}
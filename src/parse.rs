@@ -0,0 +1,72 @@
+use std::fmt;
+use std::error::Error;
+use std::io;
+
+// Errors from reading data files
+#[derive(Debug)]
+pub enum ParseError {
+    Io(io::Error),
+    // Line number (1 based) and the field that is not a number
+    InvalidNumber { line: usize, field: String },
+    // Line number (1 based) and what is wrong with it
+    InvalidFormat { line: usize, message: String },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Io(err) => write!(f, "I/O error: {}", err),
+            ParseError::InvalidNumber { line, field } =>
+                write!(f, "line {}: invalid number '{}'", line, field),
+            ParseError::InvalidFormat { line, message } =>
+                write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(err: io::Error) -> ParseError {
+        ParseError::Io(err)
+    }
+}
+
+// Lines that carry data, with their 1 based line numbers
+// Blank lines and lines starting with '#' are skipped
+pub fn data_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines().enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+}
+
+pub fn parse_number(line: usize, field: &str) -> Result<f64, ParseError> {
+    field.trim().parse::<f64>().map_err(|_| ParseError::InvalidNumber{
+        line, field: field.trim().to_string() })
+}
+
+// Comma separated numbers, one row per data line,
+// every row must have the same number of columns
+pub fn csv_numbers(text: &str) -> Result<Vec<Vec<f64>>, ParseError> {
+    let mut rows: Vec<Vec<f64>> = Vec::new();
+    for (line, content) in data_lines(text) {
+        let row = content.split(',')
+            .map(|field| parse_number(line, field))
+            .collect::<Result<Vec<f64>, ParseError>>()?;
+        if let Some(first) = rows.first() {
+            if first.len() != row.len() {
+                return Err(ParseError::InvalidFormat{ line, message: format!(
+                    "expected {} columns, found {}", first.len(), row.len()) });
+            }
+        }
+        rows.push(row);
+    }
+    Ok(rows)
+}
@@ -6,10 +6,8 @@ pub fn get_pauli_x() -> Array2<Complex<f64>> {
     let p2 = Complex::new(1f64,0f64);
     let p3 = Complex::new(1f64,0f64);
     let p4 = Complex::new(0f64,0f64);
-    let pauli = arr2(&[[p1,p2],
-                       [p3,p4]]);
-
-    pauli
+    arr2(&[[p1,p2],
+           [p3,p4]])
 }
 
 pub fn get_pauli_y() -> Array2<Complex<f64>> {
@@ -17,10 +15,8 @@ pub fn get_pauli_y() -> Array2<Complex<f64>> {
     let p2 = Complex::new(0f64,-1f64);
     let p3 = Complex::new(0f64,1f64);
     let p4 = Complex::new(0f64,0f64);
-    let pauli = arr2(&[[p1,p2],
-                       [p3,p4]]);
-
-    pauli
+    arr2(&[[p1,p2],
+           [p3,p4]])
 }
 
 pub fn get_pauli_z() -> Array2<Complex<f64>> {
@@ -28,8 +24,6 @@ pub fn get_pauli_z() -> Array2<Complex<f64>> {
     let p2 = Complex::new(0f64,0f64);
     let p3 = Complex::new(0f64,0f64);
     let p4 = Complex::new(-1f64,0f64);
-    let pauli = arr2(&[[p1,p2],
-                       [p3,p4]]);
-
-    pauli
+    arr2(&[[p1,p2],
+           [p3,p4]])
 }
@@ -0,0 +1,347 @@
+// SVG charts of trajectories and landing dispersion
+//
+// A Plot holds lines, point scatters and ellipses in data coordinates
+// and renders them with axes, ticks and a legend to an SVG string or file
+// Axes are scaled independently, so shapes are only true to scale when
+// the data ranges match the plot's aspect ratio
+// https://www.w3.org/TR/SVG11/
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::vector3::Vector3;
+use crate::trajectory::TrajectoryResult;
+use crate::dispersion::{Dispersion, Ellipse};
+
+// Size in pixels and margins around the plotting area
+const WIDTH: f64 = 800.;
+const HEIGHT: f64 = 500.;
+const MARGIN_LEFT: f64 = 70.;
+const MARGIN_RIGHT: f64 = 20.;
+const MARGIN_TOP: f64 = 40.;
+const MARGIN_BOTTOM: f64 = 50.;
+// Roughly how many ticks per axis
+const TARGET_TICKS: f64 = 8.;
+// Vertices of the polygon drawn for an ellipse
+const ELLIPSE_POINTS: usize = 72;
+const COLORS: [&str; 8] = ["#1f77b4", "#ff7f0e", "#2ca02c", "#d62728",
+    "#9467bd", "#8c564b", "#e377c2", "#17becf"];
+
+// Which projection of a trajectory to draw
+#[derive(Copy, Clone)]
+pub enum View {
+    // Horizontal distance from launch against height
+    Side,
+    // x against y from above, y to the left of the x axis
+    Top,
+}
+
+#[derive(Clone)]
+enum Mark {
+    Line(Vec<(f64, f64)>),
+    Points(Vec<(f64, f64)>),
+}
+
+#[derive(Clone)]
+struct Series {
+    label: String,
+    mark: Mark,
+}
+
+#[derive(Clone)]
+pub struct Plot {
+    pub title: String,
+    pub x_label: String,
+    pub y_label: String,
+    series: Vec<Series>,
+    _private: (),
+}
+
+impl View {
+    pub fn project(&self, position: Vector3, start: Vector3) -> (f64, f64) {
+        match self {
+            View::Side => {
+                let (dx, dy) = (position.x - start.x, position.y - start.y);
+                ((dx.powi(2) + dy.powi(2)).sqrt(), position.z)
+            },
+            View::Top => (position.x, position.y),
+        }
+    }
+}
+
+impl Plot {
+    pub fn new(title: &str, x_label: &str, y_label: &str) -> Plot {
+        Plot{ title: title.to_string(), x_label: x_label.to_string(),
+            y_label: y_label.to_string(), series: Vec::new(), _private: () }
+    }
+
+    pub fn add_line(&mut self, label: &str, points: Vec<(f64, f64)>) {
+        self.series.push(Series{ label: label.to_string(), mark: Mark::Line(points) });
+    }
+
+    pub fn add_points(&mut self, label: &str, points: Vec<(f64, f64)>) {
+        self.series.push(Series{ label: label.to_string(), mark: Mark::Points(points) });
+    }
+
+    // Outline of a dispersion ellipse in the horizontal plane
+    pub fn add_ellipse(&mut self, label: &str, ellipse: &Ellipse) {
+        let (sin_o, cos_o) = ellipse.orientation.sin_cos();
+        let outline = (0..=ELLIPSE_POINTS).map(|i| {
+            let angle = 2. * std::f64::consts::PI * i as f64 / ELLIPSE_POINTS as f64;
+            let (a, b) = (ellipse.semi_major * angle.cos(),
+                ellipse.semi_minor * angle.sin());
+            (ellipse.center.x + a * cos_o - b * sin_o,
+                ellipse.center.y + a * sin_o + b * cos_o)
+        }).collect();
+        self.add_line(label, outline);
+    }
+
+    pub fn add_trajectory(&mut self, label: &str, trajectory: &TrajectoryResult,
+        view: View) {
+        let start = trajectory.first().position;
+        self.add_line(label, trajectory.samples.iter()
+            .map(|s| view.project(s.position, start)).collect());
+    }
+
+    // Overlaid trajectories, one legend entry each
+    pub fn trajectories(view: View, shots: &[(&str, &TrajectoryResult)]) -> Plot {
+        let mut plot = match view {
+            View::Side => Plot::new("Side view", "Distance (m)", "Height (m)"),
+            View::Top => Plot::new("Top view", "x (m)", "y (m)"),
+        };
+        for (label, trajectory) in shots {
+            plot.add_trajectory(label, trajectory, view);
+        }
+        plot
+    }
+
+    // Landing points from above with an ellipse for each n_sigma
+    pub fn dispersion(dispersion: &Dispersion, n_sigmas: &[f64]) -> Plot {
+        let mut plot = Plot::new("Landing dispersion", "x (m)", "y (m)");
+        plot.add_points("Landings", dispersion.landings.iter()
+            .map(|p| (p.x, p.y)).collect());
+        for n_sigma in n_sigmas {
+            plot.add_ellipse(&format!("{} sigma", n_sigma),
+                &dispersion.ellipse(*n_sigma));
+        }
+        plot
+    }
+
+    pub fn to_svg(&self) -> String {
+        let points = self.series.iter().flat_map(|s| match &s.mark {
+            Mark::Line(points) | Mark::Points(points) => points.iter(),
+        }).filter(|(x, y)| x.is_finite() && y.is_finite());
+        let (x_min, x_max, y_min, y_max) = points.fold(
+            (f64::INFINITY, f64::NEG_INFINITY, f64::INFINITY, f64::NEG_INFINITY),
+            |(x0, x1, y0, y1), &(x, y)| (x0.min(x), x1.max(x), y0.min(y), y1.max(y)));
+        let x_axis = Axis::new(x_min, x_max);
+        let y_axis = Axis::new(y_min, y_max);
+
+        let (left, right) = (MARGIN_LEFT, WIDTH - MARGIN_RIGHT);
+        let (top, bottom) = (MARGIN_TOP, HEIGHT - MARGIN_BOTTOM);
+        let to_px = |x: f64, y: f64| {
+            (left + (x - x_axis.min) / (x_axis.max - x_axis.min) * (right - left),
+                bottom - (y - y_axis.min) / (y_axis.max - y_axis.min) * (bottom - top))
+        };
+
+        let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" \
+            width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" \
+            font-family=\"sans-serif\" font-size=\"12\">\n\
+            <rect width=\"{w}\" height=\"{h}\" fill=\"white\"/>\n",
+            w = WIDTH, h = HEIGHT);
+
+        // Grid, ticks and labels
+        for x in x_axis.ticks() {
+            let (px, _) = to_px(x, y_axis.min);
+            svg.push_str(&format!("<line x1=\"{px:.1}\" y1=\"{top}\" x2=\"{px:.1}\" \
+                y2=\"{bottom}\" stroke=\"#e0e0e0\"/>\n\
+                <text x=\"{px:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>\n",
+                bottom + 16., x_axis.format(x)));
+        }
+        for y in y_axis.ticks() {
+            let (_, py) = to_px(x_axis.min, y);
+            svg.push_str(&format!("<line x1=\"{left}\" y1=\"{py:.1}\" x2=\"{right}\" \
+                y2=\"{py:.1}\" stroke=\"#e0e0e0\"/>\n\
+                <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>\n",
+                left - 6., py + 4., y_axis.format(y)));
+        }
+        svg.push_str(&format!("<rect x=\"{left}\" y=\"{top}\" width=\"{:.1}\" \
+            height=\"{:.1}\" fill=\"none\" stroke=\"black\"/>\n",
+            right - left, bottom - top));
+        svg.push_str(&format!("<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" \
+            font-size=\"16\">{}</text>\n", (left + right) / 2., top - 14.,
+            escape(&self.title)));
+        svg.push_str(&format!("<text x=\"{:.1}\" y=\"{:.1}\" \
+            text-anchor=\"middle\">{}</text>\n", (left + right) / 2., HEIGHT - 10.,
+            escape(&self.x_label)));
+        svg.push_str(&format!("<text transform=\"translate(16 {:.1}) rotate(-90)\" \
+            text-anchor=\"middle\">{}</text>\n", (top + bottom) / 2.,
+            escape(&self.y_label)));
+
+        // Data, clipped to the plotting area by a nested viewport in the
+        // same coordinates, so that plots embedded together share no ids
+        svg.push_str(&format!("<svg x=\"{left}\" y=\"{top}\" width=\"{w:.1}\" \
+            height=\"{h:.1}\" viewBox=\"{left} {top} {w:.1} {h:.1}\" overflow=\"hidden\">\n",
+            w = right - left, h = bottom - top));
+        for (i, series) in self.series.iter().enumerate() {
+            let color = COLORS[i % COLORS.len()];
+            match &series.mark {
+                Mark::Line(points) => {
+                    let path: Vec<String> = points.iter()
+                        .filter(|(x, y)| x.is_finite() && y.is_finite())
+                        .map(|&(x, y)| {
+                            let (px, py) = to_px(x, y);
+                            format!("{:.2},{:.2}", px, py)
+                        }).collect();
+                    if path.len() >= 2 {
+                        svg.push_str(&format!("<polyline points=\"{}\" fill=\"none\" \
+                            stroke=\"{}\" stroke-width=\"1.5\"/>\n", path.join(" "), color));
+                    }
+                },
+                Mark::Points(points) => {
+                    for &(x, y) in points.iter()
+                        .filter(|(x, y)| x.is_finite() && y.is_finite()) {
+                        let (px, py) = to_px(x, y);
+                        svg.push_str(&format!("<circle cx=\"{:.2}\" cy=\"{:.2}\" \
+                            r=\"2.5\" fill=\"{}\" fill-opacity=\"0.6\"/>\n",
+                            px, py, color));
+                    }
+                },
+            }
+        }
+        svg.push_str("</svg>\n");
+
+        // Legend in the top right corner, unlabelled series are left out
+        let labelled = self.series.iter().enumerate()
+            .filter(|(_, series)| !series.label.is_empty());
+        let rows = labelled.clone().count();
+        if rows > 0 {
+            svg.push_str(&format!("<rect x=\"{:.1}\" y=\"{:.1}\" width=\"146\" \
+                height=\"{:.1}\" fill=\"white\" fill-opacity=\"0.8\" \
+                stroke=\"#c0c0c0\"/>\n", right - 156., top + 4., 18. * rows as f64 + 6.));
+        }
+        for (row, (i, series)) in labelled.enumerate() {
+            let color = COLORS[i % COLORS.len()];
+            let y = top + 16. + 18. * row as f64;
+            let x = right - 150.;
+            let marker = match series.mark {
+                Mark::Line(_) => format!("<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" \
+                    y2=\"{:.1}\" stroke=\"{}\" stroke-width=\"2\"/>",
+                    x, y - 4., x + 20., y - 4., color),
+                Mark::Points(_) => format!("<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" \
+                    fill=\"{}\"/>", x + 10., y - 4., color),
+            };
+            svg.push_str(&format!("{}\n<text x=\"{:.1}\" y=\"{:.1}\">{}</text>\n",
+                marker, x + 26., y, escape(&series.label)));
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    pub fn write_svg<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_svg())
+    }
+}
+
+// Axis range extended to whole tick steps of 1, 2 or 5 times a power of 10
+struct Axis {
+    min: f64,
+    max: f64,
+    step: f64,
+}
+
+impl Axis {
+    fn new(min: f64, max: f64) -> Axis {
+        let (min, max) = if !(min.is_finite() && max.is_finite()) {
+            (0., 1.)
+        } else if max - min <= 0. {
+            let half = if min == 0. { 1. } else { min.abs() / 2. };
+            (min - half, max + half)
+        } else {
+            (min, max)
+        };
+        let rough = (max - min) / TARGET_TICKS;
+        let magnitude = 10f64.powf(rough.log10().floor());
+        let step = [1., 2., 5., 10.].iter().map(|m| m * magnitude)
+            .find(|step| *step >= rough).unwrap_or(10. * magnitude);
+        Axis{ min: (min / step).floor() * step, max: (max / step).ceil() * step, step }
+    }
+
+    fn ticks(&self) -> Vec<f64> {
+        let n = ((self.max - self.min) / self.step).round() as usize;
+        (0..=n).map(|i| self.min + i as f64 * self.step).collect()
+    }
+
+    // Enough decimals to tell the ticks apart
+    fn format(&self, value: f64) -> String {
+        let decimals = (-self.step.log10().floor()).max(0.) as usize;
+        let text = format!("{:.*}", decimals, value);
+        // No "-0"
+        if text.trim_start_matches('-').chars().all(|c| c == '0' || c == '.') {
+            text.trim_start_matches('-').to_string()
+        } else {
+            text
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tags balance, attributes are quoted, text has no bare markup
+    // characters and there is a single root element
+    fn assert_well_formed(svg: &str) {
+        assert!(!svg.contains("NaN") && !svg.contains("inf"));
+        let mut open: Vec<&str> = Vec::new();
+        let mut closed_root = false;
+        let mut rest = svg;
+        while let Some(start) = rest.find('<') {
+            let text = &rest[..start];
+            assert!(!text.contains('>'));
+            for (i, _) in text.match_indices('&') {
+                assert!(["&amp;", "&lt;", "&gt;", "&quot;"].iter()
+                    .any(|entity| text[i..].starts_with(entity)));
+            }
+            if !text.trim().is_empty() {
+                assert!(!open.is_empty(), "text outside the root");
+            }
+            let end = start + rest[start..].find('>').expect("unclosed tag");
+            let tag = &rest[start + 1..end];
+            assert!(tag.matches('"').count().is_multiple_of(2), "unbalanced quotes");
+            assert!(!closed_root, "more than one root element");
+            if let Some(name) = tag.strip_prefix('/') {
+                assert_eq!(open.pop(), Some(name.trim()));
+            } else if !tag.ends_with('/') {
+                open.push(tag.split_whitespace().next().unwrap());
+            }
+            closed_root = open.is_empty();
+            rest = &rest[end + 1..];
+        }
+        assert!(closed_root && rest.trim().is_empty());
+    }
+
+    #[test]
+    fn empty_plot_is_well_formed() {
+        let svg = Plot::new("Nothing <yet> & more", "x", "y").to_svg();
+        assert_well_formed(&svg);
+    }
+
+    #[test]
+    fn single_point_plot_is_well_formed() {
+        let mut plot = Plot::new("One point", "x", "y");
+        plot.add_points("Point", vec![(1., 2.)]);
+        plot.add_line("Line", vec![(1., 2.)]);
+        let svg = plot.to_svg();
+        assert_well_formed(&svg);
+        assert!(svg.contains("<circle"));
+        assert!(!svg.contains("<polyline"));
+    }
+}
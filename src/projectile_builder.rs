@@ -0,0 +1,366 @@
+// Validated construction of projectiles and presets for common balls
+//
+// Projectile::new() leaves mass and radius at zero, which gives NaN
+// accelerations. ProjectileBuilder::build checks the physical parameters
+// and returns a ProjectileError instead
+// Preset sizes are the middle of the ranges allowed by the rules of each
+// sport, except the golf ball: its rules only set a maximum mass and a
+// minimum diameter, and balls are made at those limits. Aerodynamic
+// coefficients are approximate values read off published wind tunnel
+// and trajectory measurements
+
+use std::fmt;
+use std::error::Error;
+use std::sync::Arc;
+
+use crate::vector3::Vector3;
+use crate::si_units as units;
+use crate::aerodynamics::{AeroModel, ConstantCoefficients, TabulatedCoefficients};
+use crate::spin_decay::SpinDecay;
+use crate::earth_frame::EarthFrame;
+use crate::projectiles::Projectile;
+use crate::shot_parameters::ShotParameters;
+
+// Parameter of a projectile that cannot be simulated, with its value
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProjectileError {
+    // Mass must be positive and finite
+    InvalidMass(f64),
+    // Radius must be positive and finite
+    InvalidRadius(f64),
+    // Drag coefficient must be non negative and finite
+    InvalidDragCoefficient(f64),
+    // Magnus coefficient must be finite
+    InvalidMagnusCoefficient(f64),
+    // Time constant or moment of inertia of the spin decay must be
+    // positive and its torque coefficient non negative
+    InvalidSpinDecay(f64),
+    // Position, speed, angles or spin that are not finite
+    NonFiniteState,
+}
+
+#[derive(Clone)]
+pub struct ProjectileBuilder {
+    projectile: Projectile,
+    // Applied after the launch angles, which set the backspin axis
+    spin_rate_axis: Option<(f64, f64)>,
+}
+
+impl fmt::Display for ProjectileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProjectileError::InvalidMass(mass) =>
+                write!(f, "mass {} is not positive", mass),
+            ProjectileError::InvalidRadius(radius) =>
+                write!(f, "radius {} is not positive", radius),
+            ProjectileError::InvalidDragCoefficient(cd) =>
+                write!(f, "drag coefficient {} is negative or not finite", cd),
+            ProjectileError::InvalidMagnusCoefficient(coefficient) =>
+                write!(f, "Magnus coefficient {} is not finite", coefficient),
+            ProjectileError::InvalidSpinDecay(value) =>
+                write!(f, "spin decay parameter {} is out of range", value),
+            ProjectileError::NonFiniteState =>
+                write!(f, "position, velocity or spin is not finite"),
+        }
+    }
+}
+
+impl Error for ProjectileError {}
+
+impl Default for ProjectileBuilder {
+    fn default() -> ProjectileBuilder {
+        ProjectileBuilder::new()
+    }
+}
+
+impl ProjectileBuilder {
+    // Mass and radius have to be set before building
+    pub fn new() -> ProjectileBuilder {
+        ProjectileBuilder{ projectile: Projectile::new(), spin_rate_axis: None }
+    }
+
+    pub fn mass(mut self, mass: f64) -> ProjectileBuilder {
+        self.projectile.mass = mass;
+        self
+    }
+
+    pub fn radius(mut self, radius: f64) -> ProjectileBuilder {
+        self.projectile.radius = radius;
+        self
+    }
+
+    pub fn diameter(self, diameter: f64) -> ProjectileBuilder {
+        self.radius(diameter / 2.)
+    }
+
+    pub fn drag_coefficient(mut self, drag_coefficient: f64) -> ProjectileBuilder {
+        self.projectile.drag_coefficient = drag_coefficient;
+        self
+    }
+
+    pub fn magnus_coefficient(mut self, magnus_coefficient: f64) -> ProjectileBuilder {
+        self.projectile.magnus_coefficient = magnus_coefficient;
+        self
+    }
+
+    pub fn aero_model(mut self, aero_model: Arc<dyn AeroModel>) -> ProjectileBuilder {
+        self.projectile.aero_model = Some(aero_model);
+        self
+    }
+
+    pub fn spin_decay(mut self, spin_decay: SpinDecay) -> ProjectileBuilder {
+        self.projectile.spin_decay = spin_decay;
+        self
+    }
+
+    pub fn earth_frame(mut self, earth_frame: EarthFrame) -> ProjectileBuilder {
+        self.projectile.earth_frame = Some(earth_frame);
+        self
+    }
+
+    pub fn position(mut self, position: Vector3) -> ProjectileBuilder {
+        self.projectile.position = position;
+        self
+    }
+
+    pub fn speed_theta_phi(mut self, speed: f64, theta: f64, phi: f64)
+        -> ProjectileBuilder {
+        self.projectile.set_speed_theta_phi(speed, theta, phi);
+        self
+    }
+
+    pub fn velocity(mut self, velocity: Vector3) -> ProjectileBuilder {
+        self.projectile.set_velocity(velocity);
+        self
+    }
+
+    // Spin vector, replaces any spin rate and axis
+    pub fn spin(mut self, spin: Vector3) -> ProjectileBuilder {
+        self.projectile.spin = spin;
+        self.spin_rate_axis = None;
+        self
+    }
+
+    // Spin rate and axis tilt as in Projectile::set_spin_rate_axis,
+    // relative to the launch azimuth whenever it is set
+    pub fn spin_rate_axis(mut self, spin_rate: f64, spin_axis: f64)
+        -> ProjectileBuilder {
+        self.spin_rate_axis = Some((spin_rate, spin_axis));
+        self
+    }
+
+    // Launch monitor style speed, angles and spin
+    pub fn shot(self, shot: &ShotParameters) -> ProjectileBuilder {
+        self.speed_theta_phi(shot.ball_speed, shot.launch_angle,
+            -shot.launch_direction)
+            .spin_rate_axis(shot.spin_rate, shot.spin_axis)
+    }
+
+    pub fn build(self) -> Result<Projectile, ProjectileError> {
+        let mut projectile = self.projectile;
+        if let Some((spin_rate, spin_axis)) = self.spin_rate_axis {
+            projectile.set_spin_rate_axis(spin_rate, spin_axis);
+        }
+
+        if !(projectile.mass.is_finite() && projectile.mass > 0.) {
+            return Err(ProjectileError::InvalidMass(projectile.mass));
+        }
+        if !(projectile.radius.is_finite() && projectile.radius > 0.) {
+            return Err(ProjectileError::InvalidRadius(projectile.radius));
+        }
+        if !(projectile.drag_coefficient.is_finite()
+            && projectile.drag_coefficient >= 0.) {
+            return Err(ProjectileError::InvalidDragCoefficient(
+                projectile.drag_coefficient));
+        }
+        if !projectile.magnus_coefficient.is_finite() {
+            return Err(ProjectileError::InvalidMagnusCoefficient(
+                projectile.magnus_coefficient));
+        }
+        match projectile.spin_decay {
+            SpinDecay::Constant => {},
+            SpinDecay::Exponential { time_constant } => {
+                if !(time_constant.is_finite() && time_constant > 0.) {
+                    return Err(ProjectileError::InvalidSpinDecay(time_constant));
+                }
+            },
+            SpinDecay::Torque { coefficient, moment_of_inertia } => {
+                if !(coefficient.is_finite() && coefficient >= 0.) {
+                    return Err(ProjectileError::InvalidSpinDecay(coefficient));
+                }
+                if !(moment_of_inertia.is_finite() && moment_of_inertia > 0.) {
+                    return Err(ProjectileError::InvalidSpinDecay(moment_of_inertia));
+                }
+            },
+        }
+        let finite = |v: Vector3| v.x.is_finite() && v.y.is_finite() && v.z.is_finite();
+        if !(finite(projectile.position) && finite(projectile.get_velocity())
+            && finite(projectile.spin)) {
+            return Err(ProjectileError::NonFiniteState);
+        }
+        Ok(projectile)
+    }
+
+
+    /******
+    *******
+    Presets
+    *******
+    ******/
+
+    // USGA limits: at most 45.93 g, at least 42.67 mm across
+    // Spin decays by about 4% per second
+    pub fn golf_ball() -> ProjectileBuilder {
+        ProjectileBuilder::new()
+            .mass(45.93 * units::G)
+            .diameter(42.67 * units::MM)
+            .drag_coefficient(0.25)
+            .aero_model(Arc::new(TabulatedCoefficients::golf_ball()))
+            .spin_decay(SpinDecay::Exponential{ time_constant: 25. * units::S })
+    }
+
+    // MLB rules: 5 - 5.25 oz, 9 - 9.25 in around
+    // Cd from Nathan (2008), Cl from the Sawicki, Hubbard and Stronge
+    // (2003) fit, 1.5 S up to S = 0.1 and 0.09 + 0.6 S above it
+    pub fn baseball() -> ProjectileBuilder {
+        let drag = vec![
+            (5.0e4, 0.50), (1.0e5, 0.45), (1.5e5, 0.38), (2.0e5, 0.35),
+            (3.0e5, 0.35)];
+        let lift = vec![
+            (0.0, 0.00), (0.1, 0.15), (0.2, 0.21), (0.3, 0.27), (0.4, 0.33)];
+        ProjectileBuilder::new()
+            .mass(5.125 * units::OZ)
+            .diameter(9.125 * units::IN / std::f64::consts::PI)
+            .drag_coefficient(0.35)
+            .aero_model(Arc::new(TabulatedCoefficients::new(drag, lift)))
+    }
+
+    // FIFA size 5: 410 - 450 g, 68 - 70 cm around
+    // Drag crisis between Re 2e5 and 4e5 as measured by Asai et al. (2007)
+    pub fn soccer_ball() -> ProjectileBuilder {
+        let drag = vec![
+            (1.0e5, 0.47), (2.0e5, 0.45), (2.5e5, 0.35), (3.0e5, 0.25),
+            (4.0e5, 0.20), (6.0e5, 0.22)];
+        let lift = vec![
+            (0.0, 0.00), (0.1, 0.12), (0.2, 0.20), (0.3, 0.25), (0.5, 0.30)];
+        ProjectileBuilder::new()
+            .mass(430. * units::G)
+            .diameter(69. * units::CM / std::f64::consts::PI)
+            .drag_coefficient(0.25)
+            .aero_model(Arc::new(TabulatedCoefficients::new(drag, lift)))
+    }
+
+    // ITF type 2: 56.0 - 59.4 g, 6.54 - 6.86 cm across
+    // Cd 0.55 independent of Re and Stepanek's (1988) Cl = 1 / (2 + 1 / S)
+    pub fn tennis_ball() -> ProjectileBuilder {
+        let drag = vec![(1.0e4, 0.55), (3.0e5, 0.55)];
+        let lift = vec![
+            (0.0, 0.000), (0.1, 0.083), (0.2, 0.143), (0.3, 0.188),
+            (0.5, 0.250), (0.8, 0.308), (1.0, 0.333)];
+        ProjectileBuilder::new()
+            .mass(57.7 * units::G)
+            .diameter(6.7 * units::CM)
+            .drag_coefficient(0.55)
+            .aero_model(Arc::new(TabulatedCoefficients::new(drag, lift)))
+    }
+
+    // ITTF: 2.7 g, 40 mm across, smooth and well below the drag crisis
+    pub fn table_tennis_ball() -> ProjectileBuilder {
+        let drag = vec![(1.0e4, 0.50), (6.0e4, 0.45)];
+        let lift = vec![
+            (0.0, 0.00), (0.1, 0.06), (0.3, 0.15), (0.6, 0.25), (1.0, 0.30)];
+        ProjectileBuilder::new()
+            .mass(2.7 * units::G)
+            .diameter(40. * units::MM)
+            .drag_coefficient(0.47)
+            .aero_model(Arc::new(TabulatedCoefficients::new(drag, lift)))
+    }
+
+    // MCC men's ball: 155.9 - 163 g, 22.4 - 22.9 cm around
+    // The seam's swing force is not modelled
+    pub fn cricket_ball() -> ProjectileBuilder {
+        let drag = vec![
+            (5.0e4, 0.50), (1.5e5, 0.45), (2.5e5, 0.35), (4.0e5, 0.35)];
+        let lift = vec![
+            (0.0, 0.00), (0.1, 0.08), (0.2, 0.13), (0.4, 0.20)];
+        ProjectileBuilder::new()
+            .mass(159.5 * units::G)
+            .diameter(22.65 * units::CM / std::f64::consts::PI)
+            .drag_coefficient(0.45)
+            .aero_model(Arc::new(TabulatedCoefficients::new(drag, lift)))
+    }
+
+    // World Athletics men's shot: 7.26 kg, 110 - 130 mm across
+    pub fn shot_put() -> ProjectileBuilder {
+        ProjectileBuilder::new()
+            .mass(7.26 * units::KG)
+            .diameter(120. * units::MM)
+            .drag_coefficient(0.47)
+            .aero_model(Arc::new(ConstantCoefficients::new(0.47, 0.)))
+    }
+}
+
+impl Projectile {
+    pub fn builder() -> ProjectileBuilder {
+        ProjectileBuilder::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ball() -> ProjectileBuilder {
+        ProjectileBuilder::new().mass(0.1).radius(0.03)
+    }
+
+    #[test]
+    fn presets_build() {
+        let presets = [
+            ProjectileBuilder::golf_ball(), ProjectileBuilder::baseball(),
+            ProjectileBuilder::soccer_ball(), ProjectileBuilder::tennis_ball(),
+            ProjectileBuilder::table_tennis_ball(),
+            ProjectileBuilder::cricket_ball(), ProjectileBuilder::shot_put(),
+        ];
+        for preset in presets {
+            let projectile = preset.speed_theta_phi(30., 0.3, 0.)
+                .spin_rate_axis(100., 0.1).build().unwrap();
+            assert!(projectile.mass > 0. && projectile.radius > 0.);
+            assert!(projectile.aero_model.is_some());
+            assert!((projectile.get_speed() - 30.).abs() < 1e-12);
+            assert!((projectile.spin.mag() - 100.).abs() < 1e-9);
+        }
+        let golf = ProjectileBuilder::golf_ball().build().unwrap();
+        assert!((golf.mass - 45.93e-3).abs() < 1e-12);
+        assert!((golf.radius - 21.335e-3).abs() < 1e-12);
+    }
+
+    #[test]
+    fn unphysical_parameters_are_errors() {
+        let err = |builder: ProjectileBuilder| builder.build().err().unwrap();
+        assert_eq!(err(ProjectileBuilder::new()), ProjectileError::InvalidMass(0.));
+        assert_eq!(err(ball().mass(-1.)), ProjectileError::InvalidMass(-1.));
+        assert!(matches!(err(ball().mass(f64::NAN)), ProjectileError::InvalidMass(_)));
+        assert_eq!(err(ball().radius(0.)), ProjectileError::InvalidRadius(0.));
+        assert_eq!(err(ball().radius(f64::INFINITY)),
+            ProjectileError::InvalidRadius(f64::INFINITY));
+        assert_eq!(err(ball().drag_coefficient(-0.1)),
+            ProjectileError::InvalidDragCoefficient(-0.1));
+        assert_eq!(err(ball().magnus_coefficient(f64::INFINITY)),
+            ProjectileError::InvalidMagnusCoefficient(f64::INFINITY));
+        assert_eq!(err(ball().spin_decay(SpinDecay::Exponential{ time_constant: 0. })),
+            ProjectileError::InvalidSpinDecay(0.));
+        assert_eq!(err(ball().spin_decay(SpinDecay::Torque{
+            coefficient: -1., moment_of_inertia: 1e-5 })),
+            ProjectileError::InvalidSpinDecay(-1.));
+        assert_eq!(err(ball().spin_decay(SpinDecay::Torque{
+            coefficient: 1e-6, moment_of_inertia: 0. })),
+            ProjectileError::InvalidSpinDecay(0.));
+        assert_eq!(err(ball().speed_theta_phi(f64::NAN, 0.3, 0.)),
+            ProjectileError::NonFiniteState);
+        assert_eq!(err(ball().spin(Vector3::new(0., f64::INFINITY, 0.))),
+            ProjectileError::NonFiniteState);
+        assert!(ball().drag_coefficient(0.).build().is_ok());
+    }
+}
@@ -1,16 +1,61 @@
 use core::f64::consts;
-use libm::{cos,acos,sin,tan,atan};
+use std::sync::Arc;
+use libm::{cos,sin,tan,atan2};
 
 use crate::vector3::Vector3;
 use crate::constants;
-use crate::atmosphere::Atmosphere;
+use crate::atmosphere::AtmosphereModel;
+use crate::aerodynamics::AeroModel;
+use crate::spin_decay::SpinDecay;
+use crate::earth_frame::EarthFrame;
+use crate::ground::{Ball, ShotResult, Surface};
+use crate::terrain::{FlatTerrain, Terrain};
+use crate::integrators::{Derivative, DerivativeFn, Integrator, State, MIN_STEP};
+use crate::trajectory::{Forces, TrajectoryResult, TrajectorySample};
+use crate::events::{Event, EventHit};
+
+// Equations of motion and how to record a trajectory sample from a state
+struct Dynamics<'a> {
+    derivative: &'a DerivativeFn<'a>,
+    sample: &'a dyn Fn(f64, &State) -> TrajectorySample,
+}
+
+// Air properties where the projectile is
+struct LocalAir {
+    altitude: f64,
+    density: f64,
+    viscosity: f64,
+    speed_of_sound: f64,
+    wind: Vector3,
+}
+
+impl LocalAir {
+    fn at(atm: &dyn AtmosphereModel, t: f64, position: Vector3) -> LocalAir {
+        let altitude = atm.reference_elevation() + position.z;
+        LocalAir{
+            altitude,
+            density: atm.density_at(altitude),
+            viscosity: atm.dynamic_viscosity_at(altitude),
+            speed_of_sound: atm.speed_of_sound_at(altitude),
+            wind: atm.wind(position, t),
+        }
+    }
+}
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct Projectile {
     pub mass: f64,
     pub radius: f64,
+    // Used when aero_model is None, drag = Cd * rho * A * v^2 / 2
+    // and Magnus force = magnus_coefficient * (spin x velocity)
     pub drag_coefficient: f64,
     pub magnus_coefficient: f64,
+    // Reynolds number and spin ratio dependent Cd and Cl
+    pub aero_model: Option<Arc<dyn AeroModel>>,
+    pub spin_decay: SpinDecay,
+    // Rotating Earth effects and gravity model, None for a fixed
+    // frame with constants::G
+    pub earth_frame: Option<EarthFrame>,
     pub position: Vector3,
     pub spin: Vector3,
     velocity: Vector3,
@@ -27,6 +72,9 @@ impl Projectile {
             radius: 0.,
             drag_coefficient: 0.,
             magnus_coefficient: 0.,
+            aero_model: None,
+            spin_decay: SpinDecay::Constant,
+            earth_frame: None,
             position: Vector3::new(0., 0., 0.),
             spin: Vector3::new(0., 0., 0.),
             velocity: Vector3::new(0., 0., 0.),
@@ -73,15 +121,15 @@ impl Projectile {
     // Set velocity, updates speed and angles accordingly
     pub fn set_velocity_components
         (&mut self, vx: f64, vy: f64, vz: f64) {
-        let speed = self.velocity.mag();
+        let speed = (vx.powi(2) + vy.powi(2) + vz.powi(2)).sqrt();
         let xy_mag = (vx.powi(2) + vy.powi(2)).sqrt();
-        let phi = acos(vx / xy_mag);
+        let phi = atan2(vy, vx);
 
         self.velocity.x = vx;
         self.velocity.y = vy;
         self.velocity.z = vz;
         self.speed = speed;
-        self.theta = atan(vz / xy_mag);
+        self.theta = atan2(vz, xy_mag);
         self.phi = phi;
     }
 
@@ -91,15 +139,35 @@ impl Projectile {
         let vy = velocity.y;
         let vz = velocity.z;
         let xy_mag = (vx.powi(2) + vy.powi(2)).sqrt();
-        let speed = self.velocity.mag();
-        let phi = acos(vx / xy_mag);
+        let speed = velocity.mag();
+        let phi = atan2(vy, vx);
 
         self.velocity = velocity;
         self.speed = speed;
-        self.theta = atan(vz / xy_mag);
+        self.theta = atan2(vz, xy_mag);
         self.phi = phi;
     }
 
+    // Set spin from a rate (rad/s) and an axis tilt (rad) about the launch
+    // velocity, as launch monitors report it: zero tilt is pure backspin
+    // about the horizontal axis across the launch azimuth phi, positive
+    // tilt curves the projectile to the right
+    pub fn set_spin_rate_axis
+        (&mut self, spin_rate: f64, spin_axis: f64) {
+        let (backspin_axis, normal) = self.spin_frame();
+        self.spin = (backspin_axis * cos(spin_axis) - normal * sin(spin_axis))
+            * spin_rate;
+    }
+
+    // Backspin axis, horizontal and normal to the launch velocity, and
+    // the upward axis normal to both, the axes of set_spin_rate_axis
+    pub fn spin_frame(&self) -> (Vector3, Vector3) {
+        let backspin_axis = Vector3::new(sin(self.phi), -cos(self.phi), 0.);
+        let normal = Vector3::new(-sin(self.theta) * cos(self.phi),
+            -sin(self.theta) * sin(self.phi), cos(self.theta));
+        (backspin_axis, normal)
+    }
+
     // Getters
     pub fn get_theta(&self) -> f64 {
         self.theta
@@ -126,6 +194,15 @@ impl Projectile {
         self.mass * &self.velocity
     }
 
+    // Taken from the spin decay model if it has one,
+    // otherwise that of a solid sphere
+    pub fn moment_of_inertia(&self) -> f64 {
+        match self.spin_decay {
+            SpinDecay::Torque { moment_of_inertia, .. } => moment_of_inertia,
+            _ => 0.4 * self.mass * self.radius.powi(2),
+        }
+    }
+
 
     /*******************
     ********************
@@ -134,8 +211,7 @@ impl Projectile {
     *******************/
     pub fn apex_vacuum_time(&self) -> f64 {
         let g = -constants::G;
-        let t = -self.velocity.z / g;
-        t
+        -self.velocity.z / g
     }
 
     pub fn apex_vacuum(&self) -> f64 {
@@ -170,25 +246,24 @@ impl Projectile {
     }
 
     pub fn trajectory_vaccum(
-        &mut self, end_height: f64, max_time: f64) -> Vec<Vector3> {
-
-        let mut falling: bool = false;
-        let mut traj: Vec<Vector3> = Vec::new();
-        traj.push(self.position);
-        let accel= Vector3::new(0., 0., -constants::G);
-
-        let mut t: f64 = 0.;
-        let t_step = 0.1;
-        while (self.position.z > end_height && falling) || t < max_time {
-            let new_position = self.position + self.velocity * t_step;
-            falling = self.position.z > new_position.z;
-            self.position =  new_position;
-            self.set_velocity(self.velocity + accel * t_step);
-            traj.push(self.position);
-            t = t + t_step;
-        }
-
-        traj
+        &mut self, integrator: &dyn Integrator, t_step: f64,
+        end_height: f64, max_time: f64) -> TrajectoryResult {
+
+        let accel = Vector3::new(0., 0., -constants::G);
+        // No air, so no drag, lift, spin decay, Mach or Reynolds number
+        let zero = Vector3::new(0., 0., 0.);
+        let derivative = |_t: f64, state: &State| {
+            Derivative::new(state.velocity, accel, zero)
+        };
+        let forces = Forces::new(zero, zero, accel * self.mass);
+        let sample = |t: f64, state: &State| {
+            TrajectorySample::new(t, state.position, state.velocity, state.spin,
+                forces, 0., 0.)
+        };
+
+        let dynamics = Dynamics{ derivative: &derivative, sample: &sample };
+        self.integrate(integrator, &dynamics, t_step,
+            &FlatTerrain::new(end_height), max_time, &[])
     }
 
 
@@ -197,51 +272,304 @@ impl Projectile {
     With Air Resistance
     *******************
     ******************/
-    pub fn air_resistance(&self, atm: &Atmosphere, speed: f64) -> f64 {
-        let surface_area: f64 = 4. * consts::PI * self.radius.powi(2);
-        self.drag_coefficient * atm.air_density()
-            * surface_area * speed.powi(2) / 2.
+    // Reference area for the drag and lift coefficients
+    pub fn cross_section(&self) -> f64 {
+        consts::PI * self.radius.powi(2)
     }
 
-    pub fn force(&self, atm: &Atmosphere) -> Vector3 {
-        let air_res_x = self.air_resistance(atm, self.velocity.x + atm.wind.x);
-        let air_res_y = self.air_resistance(atm, self.velocity.y + atm.wind.y);
-        let air_res_z = self.air_resistance(atm, self.velocity.z + atm.wind.z);
+    // Magnitude of the drag force at the given airspeed
+    // with the constant drag_coefficient
+    pub fn air_resistance(&self, atm: &dyn AtmosphereModel, speed: f64) -> f64 {
+        self.drag_coefficient * LocalAir::at(atm, 0., self.position).density
+            * self.cross_section() * speed.powi(2) / 2.
+    }
 
-        let magnus: Vector3 = Vector3::cross_prod(&self.spin, &self.velocity) *
-                                self.magnus_coefficient;
+    // Velocity relative to the surrounding air
+    pub fn air_velocity(&self, atm: &dyn AtmosphereModel) -> Vector3 {
+        self.velocity - atm.wind(self.position, 0.)
+    }
 
-        let force_x = magnus.x - air_res_x;
-        let force_y = magnus.y - air_res_y;
-        let force_z = magnus.z - air_res_z - constants::G;
+    // Surface speed due to spin relative to the airspeed, r * omega / v
+    pub fn spin_ratio(&self, airspeed: f64) -> f64 {
+        self.radius * self.spin.mag() / airspeed
+    }
+
+    pub fn forces(&self, atm: &dyn AtmosphereModel) -> Forces {
+        self.forces_at(&LocalAir::at(atm, 0., self.position), &self.state())
+    }
 
-        Vector3::new(force_x, force_y, force_z)
+    pub fn force(&self, atm: &dyn AtmosphereModel) -> Vector3 {
+        self.forces(atm).total()
     }
 
-    pub fn acceleration(&self, atm: &Atmosphere) -> Vector3 {
+    pub fn acceleration(&self, atm: &dyn AtmosphereModel) -> Vector3 {
         &self.force(atm) / self.mass
     }
 
+    // Panics if t_step or max_time is not finite and positive
     pub fn trajectory(
-        &mut self, atm: &Atmosphere, end_height: f64, max_time: f64) -> Vec<Vector3> {
+        &mut self, atm: &dyn AtmosphereModel, integrator: &dyn Integrator,
+        t_step: f64, end_height: f64, max_time: f64) -> TrajectoryResult {
+
+        self.trajectory_with_events(atm, integrator, t_step,
+            end_height, max_time, &[])
+    }
+
+    // Trajectory that also records user defined events,
+    // a terminal event ends the flight early
+    pub fn trajectory_with_events(
+        &mut self, atm: &dyn AtmosphereModel, integrator: &dyn Integrator,
+        t_step: f64, end_height: f64, max_time: f64, events: &[Event])
+        -> TrajectoryResult {
 
-        let mut falling: bool = false;
-        let mut traj: Vec<Vector3> = Vec::new();
-        traj.push(self.position);
+        self.trajectory_over_terrain(atm, integrator, t_step,
+            &FlatTerrain::new(end_height), max_time, events)
+    }
+
+    // Trajectory that lands where it meets the terrain
+    pub fn trajectory_over_terrain(
+        &mut self, atm: &dyn AtmosphereModel, integrator: &dyn Integrator,
+        t_step: f64, terrain: &dyn Terrain, max_time: f64, events: &[Event])
+        -> TrajectoryResult {
+
+        let projectile = self.clone();
+        let derivative = |t: f64, state: &State| {
+            let air = LocalAir::at(atm, t, state.position);
+            let force = projectile.forces_at(&air, state).total();
+            let spin_rate = projectile.spin_decay.spin_rate(state.spin,
+                air.density, projectile.radius);
+            Derivative::new(state.velocity, &force / projectile.mass, spin_rate)
+        };
+        let sample = |t: f64, state: &State| {
+            let air = LocalAir::at(atm, t, state.position);
+            let airspeed = (state.velocity - air.wind).mag();
+            let mach = airspeed / air.speed_of_sound;
+            TrajectorySample::new(t, state.position, state.velocity,
+                state.spin, projectile.forces_at(&air, state),
+                mach, projectile.reynolds(&air, airspeed))
+        };
+
+        let dynamics = Dynamics{ derivative: &derivative, sample: &sample };
+        self.integrate(integrator, &dynamics, t_step,
+            terrain, max_time, events)
+    }
+
+
+    /*****************
+    ******************
+    Bounce and Roll
+    ******************
+    *****************/
+
+    // Carry flight followed by bounces and the roll on the given surface
+    pub fn shot(
+        &mut self, atm: &dyn AtmosphereModel, integrator: &dyn Integrator,
+        t_step: f64, surface: &Surface, end_height: f64, max_time: f64)
+        -> ShotResult {
+
+        self.shot_over_terrain(atm, integrator, t_step, surface,
+            &FlatTerrain::new(end_height), max_time)
+    }
+
+    // Shot where landing, bounces and roll follow the terrain
+    pub fn shot_over_terrain(
+        &mut self, atm: &dyn AtmosphereModel, integrator: &dyn Integrator,
+        t_step: f64, surface: &Surface, terrain: &dyn Terrain, max_time: f64)
+        -> ShotResult {
+
+        // Limit on the number of bounces before forcing a roll
+        let max_bounces = 20;
+        let ball = Ball::new(self.mass, self.radius, self.moment_of_inertia());
+
+        let mut flights = vec![self.trajectory_over_terrain(
+            atm, integrator, t_step, terrain, max_time, &[])];
+        let mut roll: Vec<Vector3> = Vec::new();
+        while flights[flights.len() - 1].landing.is_some() {
+            let normal = terrain.normal(self.position.x, self.position.y);
+            let state = surface.bounce(&ball, &self.state(), normal);
+            if !surface.is_bounce(&state, normal) || flights.len() > max_bounces {
+                roll = surface.roll(&state, terrain);
+                self.position = roll[roll.len() - 1];
+                self.set_velocity(Vector3::new(0., 0., 0.));
+                self.spin = Vector3::new(0., 0., 0.);
+                break;
+            }
+
+            self.set_velocity(state.velocity);
+            self.spin = state.spin;
+            flights.push(self.trajectory_over_terrain(
+                atm, integrator, t_step, terrain, max_time, &[]));
+        }
 
+        ShotResult::new(flights, roll)
+    }
+
+
+    /*******
+    ********
+    Internal
+    ********
+    *******/
+    fn state(&self) -> State {
+        State::new(self.position, self.velocity, self.spin)
+    }
+
+    fn reynolds(&self, air: &LocalAir, airspeed: f64) -> f64 {
+        air.density * airspeed * 2. * self.radius / air.viscosity
+    }
+
+    // Drag, lift, gravity and any Earth rotation forces for the
+    // projectile at the given state
+    fn forces_at(&self, air: &LocalAir, state: &State) -> Forces {
+        let g = match &self.earth_frame {
+            Some(frame) => frame.gravity(air.altitude),
+            None => constants::G,
+        };
+        let gravity = Vector3::new(0., 0., -self.mass * g);
+        let (drag, magnus) = self.aero_forces(air, state);
+        let mut forces = Forces::new(drag, magnus, gravity);
+        if let Some(frame) = &self.earth_frame {
+            forces.coriolis = frame.coriolis(state.velocity) * self.mass;
+            forces.centrifugal = frame.centrifugal(state.position) * self.mass;
+        }
+        forces
+    }
+
+    // Drag and lift
+    fn aero_forces(&self, air: &LocalAir, state: &State) -> (Vector3, Vector3) {
+        let zero = Vector3::new(0., 0., 0.);
+        let air_velocity = state.velocity - air.wind;
+        let airspeed = air_velocity.mag();
+        if airspeed == 0. {
+            return (zero, zero);
+        }
+
+        // Force per unit coefficient, rho * A * v^2 / 2
+        let dynamic_force = air.density * self.cross_section()
+            * airspeed.powi(2) / 2.;
+        let (drag_coefficient, magnus) = match &self.aero_model {
+            None => (self.drag_coefficient,
+                Vector3::cross_prod(&state.spin, &air_velocity) *
+                    self.magnus_coefficient),
+            Some(model) => {
+                let reynolds = self.reynolds(air, airspeed);
+                let spin_ratio = self.radius * state.spin.mag() / airspeed;
+                let lift_coefficient = model.lift_coefficient(reynolds, spin_ratio);
+                // Lift is along spin x velocity, scaled by the sine between them
+                let lift_direction = if state.spin.mag() > 0. {
+                    Vector3::cross_prod(&state.spin.normalize(),
+                        &air_velocity.normalize())
+                } else {
+                    zero
+                };
+                let mach = airspeed / air.speed_of_sound;
+                (model.drag_coefficient_mach(reynolds, spin_ratio, mach),
+                    lift_direction * (lift_coefficient * dynamic_force))
+            }
+        };
+
+        // Drag opposes the motion through the air
+        let drag = air_velocity.normalize() * -(drag_coefficient * dynamic_force);
+        (drag, magnus)
+    }
+
+    // Step the equations of motion with the given integrator until landing,
+    // a terminal user event or max_time
+    // t_step is the (initial, for adaptive integrators) step size
+    // Panics if t_step or max_time is not finite and positive
+    fn integrate(
+        &mut self, integrator: &dyn Integrator, dynamics: &Dynamics,
+        t_step: f64, terrain: &dyn Terrain, max_time: f64,
+        events: &[Event]) -> TrajectoryResult {
+
+        assert!(t_step.is_finite() && t_step > 0.,
+            "t_step must be finite and positive, got {}", t_step);
+        assert!(max_time.is_finite() && max_time > 0.,
+            "max_time must be finite and positive, got {}", max_time);
+
+        let derivative = dynamics.derivative;
+        let sample = dynamics.sample;
+
+        // Built in events first, user events are offset by 2
+        let landing = Event::landing_on(terrain);
+        let apex = Event::apex();
+        let mut all_events: Vec<&Event> = vec![&landing, &apex];
+        all_events.extend(events.iter());
+
+        let mut state = self.state();
         let mut t: f64 = 0.;
-        let t_step = 0.1;
-        while (self.position.z > end_height && falling) || t < max_time {
-            let new_position = self.position + self.velocity * t_step;
-            falling = self.position.z > new_position.z;
-            self.position = new_position;
-            let accel = self.acceleration(atm);
-            self.set_velocity(self.velocity + accel * t_step);
-            traj.push(self.position);
-            t = t + t_step;
+        let mut traj: Vec<TrajectorySample> = vec![sample(t, &state)];
+        let mut error_estimate: Option<f64> = None;
+        let mut landing_hit: Option<EventHit> = None;
+        let mut apex_hit: Option<EventHit> = None;
+        let mut user_hits: Vec<(usize, EventHit)> = Vec::new();
+
+        let mut dt = t_step;
+        let mut values: Vec<f64> = all_events.iter()
+            .map(|event| event.value(t, &state)).collect();
+        while t < max_time {
+            let step = integrator.step(derivative, t, &state,
+                dt.max(MIN_STEP).min(max_time - t));
+            // An integrator that makes no progress would never finish
+            if !(step.dt.is_finite() && step.dt > 0.) {
+                break;
+            }
+            let t_new = t + step.dt;
+            if let Some(error) = step.error {
+                error_estimate = Some(error_estimate.unwrap_or(0.) + error);
+            }
+
+            // Bracket sign changes over the step and refine them
+            let new_values: Vec<f64> = all_events.iter()
+                .map(|event| event.value(t_new, &step.state)).collect();
+            let mut hits: Vec<(usize, EventHit)> = Vec::new();
+            for (i, event) in all_events.iter().enumerate() {
+                if event.crossed(values[i], new_values[i]) {
+                    if let Some(hit) = event.locate(derivative,
+                        t, &state, t_new, &step.state) {
+                        hits.push((i, hit));
+                    }
+                }
+            }
+            hits.sort_by(|a, b| a.1.time.total_cmp(&b.1.time));
+
+            // Record events in time order up to the first terminal one
+            let mut stop: Option<EventHit> = None;
+            for (i, hit) in hits {
+                match i {
+                    0 => landing_hit = Some(hit),
+                    1 => apex_hit = Some(hit),
+                    _ => user_hits.push((i - 2, hit)),
+                }
+                if all_events[i].terminal {
+                    stop = Some(hit);
+                    break;
+                }
+            }
+            if let Some(hit) = stop {
+                state = hit.state();
+                traj.push(sample(hit.time, &state));
+                break;
+            }
+
+            state = step.state;
+            t = t_new;
+            dt = step.dt_next;
+            values = new_values;
+            traj.push(sample(t, &state));
         }
 
-        traj
+        self.position = state.position;
+        self.set_velocity(state.velocity);
+        self.spin = state.spin;
+
+        TrajectoryResult::new(traj, error_estimate,
+            landing_hit, apex_hit, user_hits)
     }
 }
 
+impl Default for Projectile {
+    fn default() -> Self {
+        Projectile::new()
+    }
+}
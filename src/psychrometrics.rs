@@ -0,0 +1,280 @@
+// Moist air properties from temperature, pressure and water vapor
+//
+// Temperatures in K, pressures in Pa, humidities as fractions (not %)
+// Ratios per unit mass are per kg of moist air (specific humidity) or
+// per kg of dry air (mixing ratio, enthalpy)
+// https://en.wikipedia.org/wiki/Psychrometrics
+
+use libm::{exp, log10};
+
+use crate::constants;
+use crate::si_units as units;
+use crate::root_finding;
+
+// Ratio of the molar masses of water vapor and dry air
+pub const EPSILON: f64 = constants::WATER_VAPOR_MOL_MASS / constants::AIR_MOL_MASS;
+
+// Specific heats at constant pressure (J/(kg*K)) and latent heat of
+// vaporization at 0 C (J/kg) used for the enthalpy
+const DRY_AIR_SPECIFIC_HEAT: f64 = 1006.;
+const VAPOR_SPECIFIC_HEAT: f64 = 1860.;
+const LATENT_HEAT: f64 = 2.501e6;
+
+// Temperatures searched when inverting the saturation curve
+const MIN_TEMPERATURE: f64 = 150. * units::K;
+const MAX_TEMPERATURE: f64 = 400. * units::K;
+const TEMPERATURE_TOL: f64 = 1e-9;
+const MAX_ITER: usize = 100;
+
+// Saturation vapor pressure formulas, over water above 0 C and
+// over ice below it, the error quoted for each is its largest departure
+// from IAPWS over water from 0 to 50 C and over ice down to -40 C
+#[derive(Copy, Clone, Default)]
+pub enum SaturationCurve {
+    // 0.2% over water, 2% over ice
+    // https://en.wikipedia.org/wiki/Tetens_equation
+    #[default]
+    Tetens,
+    // Alduchov and Eskridge (1996) Magnus form, 0.3%
+    // https://doi.org/10.1175/1520-0450(1996)035<0601:IMFAOS>2.0.CO;2
+    MagnusTetens,
+    // Buck (1996 update of the 1981 fit), 0.06%
+    // https://en.wikipedia.org/wiki/Arden_Buck_equation
+    Buck,
+    // Goff-Gratch (1946) as adopted by the WMO, 0.2%
+    // https://en.wikipedia.org/wiki/Goff%E2%80%93Gratch_equation
+    GoffGratch,
+    // IAPWS: Wagner and Pruss (2002) over water,
+    // Wagner, Riethmann, Feistel and Harvey (2011) over ice
+    Iapws,
+}
+
+impl SaturationCurve {
+    // Saturation vapor pressure at the given temperature
+    pub fn pressure(&self, temperature: f64) -> f64 {
+        let celsius = units::kelvin_to_celsius(temperature);
+        let over_ice = celsius < 0.;
+        match self {
+            SaturationCurve::Tetens => {
+                let (a, b) = if over_ice { (21.875, 265.5) } else { (17.27, 237.3) };
+                0.61078 * exp(a * celsius / (celsius + b)) * units::KPA
+            },
+            SaturationCurve::MagnusTetens => {
+                let (c, a, b) = if over_ice {
+                    (611.21, 22.587, 273.86)
+                } else {
+                    (610.94, 17.625, 243.04)
+                };
+                c * exp(a * celsius / (celsius + b)) * units::PA
+            },
+            SaturationCurve::Buck => {
+                let (c, a, d, b) = if over_ice {
+                    (0.61115, 23.036, 333.7, 279.82)
+                } else {
+                    (0.61121, 18.678, 234.5, 257.14)
+                };
+                c * exp((a - celsius / d) * (celsius / (b + celsius))) * units::KPA
+            },
+            SaturationCurve::GoffGratch => {
+                let log_hpa = if over_ice {
+                    let ratio = 273.16 / temperature;
+                    -9.09718 * (ratio - 1.) - 3.56654 * log10(ratio)
+                        + 0.876793 * (1. - 1. / ratio) + log10(6.1071)
+                } else {
+                    let ratio = 373.16 / temperature;
+                    -7.90298 * (ratio - 1.) + 5.02808 * log10(ratio)
+                        - 1.3816e-7 * (10f64.powf(11.344 * (1. - 1. / ratio)) - 1.)
+                        + 8.1328e-3 * (10f64.powf(-3.49149 * (ratio - 1.)) - 1.)
+                        + log10(1013.246)
+                };
+                10f64.powf(log_hpa) * 100. * units::PA
+            },
+            SaturationCurve::Iapws => {
+                if over_ice {
+                    let triple_temperature = 273.16;
+                    let theta = temperature / triple_temperature;
+                    let sum = -21.214_400_6 * theta.powf(0.003_333_333)
+                        + 27.320_381_9 * theta.powf(1.206_666_67)
+                        - 6.105_981_30 * theta.powf(1.703_333_33);
+                    611.657 * exp(sum / theta) * units::PA
+                } else {
+                    let critical_temperature = 647.096;
+                    let tau = 1. - temperature / critical_temperature;
+                    let sum = -7.859_517_83 * tau + 1.844_082_59 * tau.powf(1.5)
+                        - 11.786_649_7 * tau.powi(3) + 22.680_741_1 * tau.powf(3.5)
+                        - 15.961_871_9 * tau.powi(4) + 1.801_225_02 * tau.powf(7.5);
+                    22.064e6 * exp(critical_temperature / temperature * sum)
+                        * units::PA
+                }
+            },
+        }
+    }
+
+    // Temperature at which the saturation pressure is the given pressure,
+    // None outside the range of the curve
+    pub fn temperature(&self, pressure: f64) -> Option<f64> {
+        root_finding::brent(|t| self.pressure(t) - pressure,
+            MIN_TEMPERATURE, MAX_TEMPERATURE, TEMPERATURE_TOL, MAX_ITER)
+    }
+}
+
+
+/***************
+****************
+Humidity Measures
+****************
+***************/
+
+pub fn relative_humidity(vapor_pressure: f64, temperature: f64,
+    curve: &SaturationCurve) -> f64 {
+    vapor_pressure / curve.pressure(temperature)
+}
+
+pub fn vapor_pressure(relative_humidity: f64, temperature: f64,
+    curve: &SaturationCurve) -> f64 {
+    relative_humidity * curve.pressure(temperature)
+}
+
+// The vapor pressure is the saturation pressure at the dew point
+pub fn dew_point(vapor_pressure: f64, curve: &SaturationCurve) -> Option<f64> {
+    curve.temperature(vapor_pressure)
+}
+
+// Psychrometer equation for a ventilated wet bulb thermometer,
+// e = e_s(T_w) - A * p * (T - T_w)
+// https://library.wmo.int/idurl/4/68695 (WMO-No. 8, Annex 4.B)
+pub fn vapor_pressure_from_wet_bulb(temperature: f64, wet_bulb: f64,
+    pressure: f64, curve: &SaturationCurve) -> f64 {
+    let coefficient = 6.53e-4 * (1. + 9.44e-4 * units::kelvin_to_celsius(wet_bulb));
+    curve.pressure(wet_bulb) - coefficient * pressure * (temperature - wet_bulb)
+}
+
+// Wet bulb temperature, solving the psychrometer equation,
+// None if the air is supersaturated
+pub fn wet_bulb(temperature: f64, vapor_pressure: f64, pressure: f64,
+    curve: &SaturationCurve) -> Option<f64> {
+    root_finding::brent(|wet_bulb| vapor_pressure_from_wet_bulb(
+        temperature, wet_bulb, pressure, curve) - vapor_pressure,
+        MIN_TEMPERATURE, temperature, TEMPERATURE_TOL, MAX_ITER)
+}
+
+// Mass of water vapor per mass of dry air
+pub fn mixing_ratio(vapor_pressure: f64, pressure: f64) -> f64 {
+    EPSILON * vapor_pressure / (pressure - vapor_pressure)
+}
+
+pub fn vapor_pressure_from_mixing_ratio(mixing_ratio: f64, pressure: f64) -> f64 {
+    mixing_ratio * pressure / (EPSILON + mixing_ratio)
+}
+
+// Mass of water vapor per mass of moist air
+pub fn specific_humidity(vapor_pressure: f64, pressure: f64) -> f64 {
+    EPSILON * vapor_pressure / (pressure - (1. - EPSILON) * vapor_pressure)
+}
+
+// Mass of water vapor per volume (kg/m^3)
+pub fn absolute_humidity(vapor_pressure: f64, temperature: f64) -> f64 {
+    vapor_pressure * constants::WATER_VAPOR_MOL_MASS
+        / (constants::R * temperature)
+}
+
+// Temperature dry air would need for the density of the moist air
+pub fn virtual_temperature(temperature: f64, vapor_pressure: f64,
+    pressure: f64) -> f64 {
+    temperature / (1. - vapor_pressure / pressure * (1. - EPSILON))
+}
+
+// Speed of sound in moist air, treating dry air as diatomic
+// (c_p = 7R/2) and water vapor as triatomic (c_p = 4R) ideal gases
+pub fn speed_of_sound(temperature: f64, vapor_pressure: f64,
+    pressure: f64) -> f64 {
+    let vapor_fraction = vapor_pressure / pressure;
+    let molar_mass = (1. - vapor_fraction) * constants::AIR_MOL_MASS
+        + vapor_fraction * constants::WATER_VAPOR_MOL_MASS;
+    let molar_heat = (1. - vapor_fraction) * 3.5 + vapor_fraction * 4.;
+    let adiabatic_index = molar_heat / (molar_heat - 1.);
+    (adiabatic_index * constants::R * temperature / molar_mass).sqrt()
+}
+
+// Specific enthalpy (J/kg of dry air) relative to dry air and
+// liquid water at 0 C
+pub fn enthalpy(temperature: f64, mixing_ratio: f64) -> f64 {
+    let celsius = units::kelvin_to_celsius(temperature);
+    DRY_AIR_SPECIFIC_HEAT * celsius
+        + mixing_ratio * (LATENT_HEAT + VAPOR_SPECIFIC_HEAT * celsius)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVES: [(SaturationCurve, f64); 5] = [
+        (SaturationCurve::Tetens, 2e-3),
+        (SaturationCurve::MagnusTetens, 3e-3),
+        (SaturationCurve::Buck, 6e-4),
+        (SaturationCurve::GoffGratch, 2e-3),
+        (SaturationCurve::Iapws, 1e-4),
+    ];
+    const ROOM: f64 = 293.15;
+    const SEA_LEVEL: f64 = 101_325.;
+
+    // 2339 Pa at 20 C from the IAPWS formulation
+    #[test]
+    fn saturation_pressure_at_room_temperature() {
+        for (curve, tolerance) in CURVES {
+            let pressure = curve.pressure(ROOM);
+            assert!((pressure / 2339. - 1.).abs() < tolerance,
+                "{} Pa", pressure);
+            let temperature = curve.temperature(pressure).unwrap();
+            assert!((temperature - ROOM).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn dew_point_and_wet_bulb_round_trip_to_relative_humidity() {
+        for (curve, _) in CURVES {
+            for humidity in [0.1, 0.5, 0.9] {
+                let e = vapor_pressure(humidity, ROOM, &curve);
+                let dew = dew_point(e, &curve).unwrap();
+                assert!(dew < ROOM);
+                assert!((relative_humidity(curve.pressure(dew), ROOM, &curve)
+                    - humidity).abs() < 1e-9);
+
+                let wet = wet_bulb(ROOM, e, SEA_LEVEL, &curve).unwrap();
+                assert!(dew < wet && wet < ROOM);
+                let e_wet = vapor_pressure_from_wet_bulb(ROOM, wet, SEA_LEVEL, &curve);
+                assert!((relative_humidity(e_wet, ROOM, &curve)
+                    - humidity).abs() < 1e-9);
+            }
+        }
+        // Saturated air has its dew point and wet bulb at the temperature
+        let curve = SaturationCurve::Iapws;
+        let e = curve.pressure(ROOM);
+        assert!((dew_point(e, &curve).unwrap() - ROOM).abs() < 1e-6);
+        assert!((wet_bulb(ROOM, e, SEA_LEVEL, &curve).unwrap() - ROOM).abs() < 1e-6);
+    }
+
+    // 20 C and 50% at sea level, against psychrometric chart values
+    #[test]
+    fn moist_air_properties_at_room_conditions() {
+        let curve = SaturationCurve::Iapws;
+        let e = vapor_pressure(0.5, ROOM, &curve);
+        let celsius = |t: Option<f64>| units::kelvin_to_celsius(t.unwrap());
+        assert!((celsius(dew_point(e, &curve)) - 9.3).abs() < 0.05);
+        assert!((celsius(wet_bulb(ROOM, e, SEA_LEVEL, &curve)) - 13.8).abs() < 0.1);
+
+        let w = mixing_ratio(e, SEA_LEVEL);
+        assert!((w - 7.26e-3).abs() < 1e-5);
+        assert!((vapor_pressure_from_mixing_ratio(w, SEA_LEVEL) - e).abs() < 1e-9);
+        let q = specific_humidity(e, SEA_LEVEL);
+        assert!((q - w / (1. + w)).abs() < 1e-12);
+        assert!((absolute_humidity(e, ROOM) - 8.65e-3).abs() < 1e-5);
+        assert!((virtual_temperature(ROOM, e, SEA_LEVEL) - 294.44).abs() < 0.01);
+        assert!((enthalpy(ROOM, w) / 1e3 - 38.6).abs() < 0.1);
+        assert!(enthalpy(273.15, 0.).abs() < 1e-9);
+        // Dry air is 343.2 m/s, vapor makes sound a little faster
+        assert!((speed_of_sound(ROOM, 0., SEA_LEVEL) - 343.2).abs() < 0.1);
+        assert!(speed_of_sound(ROOM, e, SEA_LEVEL) > speed_of_sound(ROOM, 0., SEA_LEVEL));
+    }
+}
@@ -0,0 +1,95 @@
+// Seedable pseudo random numbers and the distributions sampled from them
+//
+// xoshiro256** seeded through SplitMix64, the same seed always gives the
+// same sequence on every platform
+// https://prng.di.unimi.it/
+
+use std::f64::consts::PI;
+
+#[derive(Clone)]
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        let mut sm = seed;
+        Rng{ state: [0; 4].map(|_| splitmix64(&mut sm)) }
+    }
+
+    // Independent generator for one member of a batch, so results do not
+    // depend on how the batch is split between threads
+    pub fn stream(seed: u64, index: u64) -> Rng {
+        let mut sm = seed;
+        Rng::new(splitmix64(&mut sm) ^ index.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    // Uniform on [0, 1)
+    pub fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Standard normal, Box-Muller transform
+    pub fn normal(&mut self) -> f64 {
+        // 1 - uniform is in (0, 1], keeping the log finite
+        let u1 = 1. - self.uniform();
+        let u2 = self.uniform();
+        (-2. * u1.ln()).sqrt() * (2. * PI * u2).cos()
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+
+/************
+*************
+Distributions
+*************
+************/
+
+#[derive(Copy, Clone)]
+pub enum Distribution {
+    // Always the same value
+    Fixed(f64),
+    Uniform { min: f64, max: f64 },
+    Normal { mean: f64, std_dev: f64 },
+}
+
+impl Distribution {
+    pub fn sample(&self, rng: &mut Rng) -> f64 {
+        match *self {
+            Distribution::Fixed(value) => value,
+            Distribution::Uniform { min, max } =>
+                min + (max - min) * rng.uniform(),
+            Distribution::Normal { mean, std_dev } =>
+                mean + std_dev * rng.normal(),
+        }
+    }
+
+    pub fn mean(&self) -> f64 {
+        match *self {
+            Distribution::Fixed(value) => value,
+            Distribution::Uniform { min, max } => (min + max) / 2.,
+            Distribution::Normal { mean, .. } => mean,
+        }
+    }
+}
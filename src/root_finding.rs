@@ -0,0 +1,82 @@
+// Scalar root finding on a bracketing interval [a, b]
+
+// Brent's method, inverse quadratic interpolation with bisection fallback
+// None if f(a) and f(b) have the same sign or are NaN
+// https://en.wikipedia.org/wiki/Brent%27s_method
+pub fn brent<F: Fn(f64) -> f64>(
+    f: F, a: f64, b: f64, tol: f64, max_iter: usize) -> Option<f64> {
+
+    let mut a = a;
+    let mut b = b;
+    let mut fa = f(a);
+    let mut fb = f(b);
+    if fa == 0. { return Some(a); }
+    if fb == 0. { return Some(b); }
+    if fa.is_nan() || fb.is_nan() || fa * fb > 0. { return None; }
+
+    let mut c = a;
+    let mut fc = fa;
+    let mut d = b - a;
+    let mut e = d;
+    for _ in 0..max_iter {
+        if fb * fc > 0. {
+            c = a;
+            fc = fa;
+            d = b - a;
+            e = d;
+        }
+        if fc.abs() < fb.abs() {
+            a = b;
+            b = c;
+            c = a;
+            fa = fb;
+            fb = fc;
+            fc = fa;
+        }
+
+        let tol1 = 2. * f64::EPSILON * b.abs() + tol / 2.;
+        let xm = (c - b) / 2.;
+        if xm.abs() <= tol1 || fb == 0. {
+            return Some(b);
+        }
+
+        if e.abs() >= tol1 && fa.abs() > fb.abs() {
+            // Attempt interpolation
+            let s = fb / fa;
+            let (mut p, mut q) = if a == c {
+                // Secant
+                (2. * xm * s, 1. - s)
+            } else {
+                // Inverse quadratic
+                let q = fa / fc;
+                let r = fb / fc;
+                (s * (2. * xm * q * (q - r) - (b - a) * (r - 1.)),
+                    (q - 1.) * (r - 1.) * (s - 1.))
+            };
+            if p > 0. { q = -q; }
+            p = p.abs();
+            let min1 = 3. * xm * q - (tol1 * q).abs();
+            let min2 = (e * q).abs();
+            if 2. * p < min1.min(min2) {
+                e = d;
+                d = p / q;
+            } else {
+                d = xm;
+                e = d;
+            }
+        } else {
+            d = xm;
+            e = d;
+        }
+
+        a = b;
+        fa = fb;
+        if d.abs() > tol1 {
+            b += d;
+        } else {
+            b += tol1.copysign(xm);
+        }
+        fb = f(b);
+    }
+    Some(b)
+}
@@ -0,0 +1,103 @@
+// Launch conditions as reported by golf launch monitors
+//
+// Launch direction and spin axis are positive to the right as seen from
+// behind the ball, so a positive launch direction starts the ball right
+// of the x axis (negative phi) and a positive spin axis curves it right
+// (see Projectile::set_spin_rate_axis)
+// Values are stored in SI units, from_monitor and to_monitor use the
+// usual readout units: mph, degrees and rpm
+
+use libm::atan2;
+
+use crate::si_units as units;
+use crate::projectiles::Projectile;
+
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct ShotParameters {
+    pub ball_speed: f64,
+    // Vertical launch angle above the horizontal
+    pub launch_angle: f64,
+    // Horizontal launch direction from the x axis, positive right
+    pub launch_direction: f64,
+    // Total spin rate (rad/s)
+    pub spin_rate: f64,
+    // Tilt of the spin axis from pure backspin, positive right
+    pub spin_axis: f64,
+    _private: (),
+}
+
+impl ShotParameters {
+    pub fn new(ball_speed: f64, launch_angle: f64, launch_direction: f64,
+        spin_rate: f64, spin_axis: f64) -> ShotParameters {
+        ShotParameters{ ball_speed, launch_angle, launch_direction,
+            spin_rate, spin_axis, _private: () }
+    }
+
+    // Ball speed (mph), launch angle and direction (deg),
+    // total spin (rpm) and spin axis (deg)
+    pub fn from_monitor(ball_speed: f64, launch_angle: f64,
+        launch_direction: f64, spin_rate: f64, spin_axis: f64) -> ShotParameters {
+        ShotParameters::new(ball_speed * units::MPH,
+            launch_angle * units::DEGREES, launch_direction * units::DEGREES,
+            spin_rate * units::RPM, spin_axis * units::DEGREES)
+    }
+
+    // Inverse of from_monitor, in the same order
+    pub fn to_monitor(&self) -> (f64, f64, f64, f64, f64) {
+        (self.ball_speed / units::MPH, self.launch_angle / units::DEGREES,
+            self.launch_direction / units::DEGREES, self.spin_rate / units::RPM,
+            self.spin_axis / units::DEGREES)
+    }
+
+    // Backspin and sidespin components of the spin rate,
+    // sidespin positive right
+    pub fn backspin(&self) -> f64 {
+        self.spin_rate * self.spin_axis.cos()
+    }
+
+    pub fn sidespin(&self) -> f64 {
+        self.spin_rate * self.spin_axis.sin()
+    }
+}
+
+impl Projectile {
+    // Set velocity and spin from launch monitor style parameters
+    pub fn set_shot(&mut self, shot: &ShotParameters) {
+        self.set_speed_theta_phi(shot.ball_speed, shot.launch_angle,
+            -shot.launch_direction);
+        self.set_spin_rate_axis(shot.spin_rate, shot.spin_axis);
+    }
+
+    // Launch monitor style parameters of the current velocity and spin
+    // Spin about the direction of flight (rifling spin) has no
+    // equivalent and is left out of the spin rate
+    pub fn shot_parameters(&self) -> ShotParameters {
+        let (backspin_axis, normal) = self.spin_frame();
+        let backspin = self.spin.dot(backspin_axis);
+        let sidespin = -self.spin.dot(normal);
+        ShotParameters::new(self.get_speed(), self.get_theta(), -self.get_phi(),
+            backspin.hypot(sidespin), atan2(sidespin, backspin))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spin_axis_tilts_about_the_launch_velocity() {
+        let shot = ShotParameters::from_monitor(90., 55., -4., 9000., 20.);
+        let mut ball = Projectile::new();
+        ball.set_shot(&shot);
+
+        // No spin about the direction of flight at any launch angle
+        assert!(ball.spin.dot(ball.get_velocity().normalize()).abs() < 1e-9);
+        let read = ball.shot_parameters();
+        assert!((read.launch_angle - shot.launch_angle).abs() < 1e-12);
+        assert!((read.launch_direction - shot.launch_direction).abs() < 1e-12);
+        assert!((read.spin_rate - shot.spin_rate).abs() < 1e-9);
+        assert!((read.spin_axis - shot.spin_axis).abs() < 1e-12);
+    }
+}
@@ -5,12 +5,16 @@ use core::f64::consts;
 // Length
 pub const M:     f64 = 1.;
 pub const KM:    f64 = 1e3 * M;
-pub const CM:    f64 = 100. * M;
+pub const CM:    f64 = 1e-2 * M;
 pub const MM:    f64 = 1e-3 * M;
 pub const UM:    f64 = 1e-6 * M;
 pub const NM:    f64 = 1e-9 * M;
 
 pub const MILES: f64 = 1_609.344 * M;
+pub const FT:    f64 = 0.304_8 * M;
+pub const IN:    f64 = 0.025_4 * M;
+pub const YD:    f64 = 0.914_4 * M;
+pub const NMI:   f64 = 1_852. * M;
 
 // Time
 pub const S:   f64 = 1.;
@@ -22,12 +26,18 @@ pub const PS:  f64 = 1e-12 * S;
 pub const HR:  f64 = 3_600. * S;
 pub const MIN: f64 = 60. * S;
 
+// Speed
+pub const KNOT: f64 = NMI / HR;
+pub const MPH:  f64 = MILES / HR;
+
 // Mass
 pub const KG: f64 = 1.;
-pub const G:  f64 = 1e3 * KG;
-pub const MG: f64 = 1e6 * KG;
+pub const G:  f64 = 1e-3 * KG;
+pub const MG: f64 = 1e-6 * KG;
 
 pub const LB: f64 = 0.453_592_4 * KG;
+pub const OZ: f64 = LB / 16.;
+pub const GR: f64 = 6.479_891e-5 * KG;
 
 // Energy
 pub const J:    f64 = KG * M * M / (S * S);
@@ -40,7 +50,10 @@ pub const N: f64 = KG * M / (S * S);
 
 // Pressure
 pub const PA:  f64 = J / (M * M * M);
-pub const KPA: f64 = 1e-3 * PA;
+pub const KPA: f64 = 1e3 * PA;
+pub const HPA: f64 = 1e2 * PA;
+
+pub const INHG: f64 = 3_386.389 * PA;
 
 // Angles
 pub const RAD:     f64  = 1.;
@@ -48,6 +61,9 @@ pub const DEGREES: f64  = 2. * consts::PI * RAD / 360.;
 pub const ARCMIN:  f64  = DEGREES / 60.;
 pub const ARCSEC:  f64  = DEGREES / 3_600.;
 
+// Angular speed
+pub const RPM: f64 = 2. * consts::PI * RAD / MIN;
+
 // Temperature
 pub const K:    f64 = 1.;
 pub const KTOC: f64 = 273.15;
@@ -83,10 +99,10 @@ pub fn degrees_to_rad(degrees: f64) -> f64 {
 
 // Temperature
 pub fn kelvin_to_celsius(kelvin: f64) -> f64 {
-    kelvin + 272.15
+    kelvin - KTOC
 }
 pub fn celsius_to_kelvin(celsius: f64) -> f64 {
-    celsius - 272.15
+    celsius + KTOC
 }
 pub fn celsius_to_farenheit(celsius: f64) -> f64 {
     celsius * 9. / 5. + 32.
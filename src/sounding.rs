@@ -0,0 +1,329 @@
+// Atmosphere built from a measured sounding (radiosonde or tower data)
+//
+// Each quantity is interpolated linearly in height between the levels
+// that report it (pressure logarithmically), and held at the nearest
+// level outside them. Wind is interpolated by components
+// Trajectory coordinates are tied to the compass by x_bearing, the
+// bearing of the x axis, with y 90 degrees anticlockwise of x
+// (north for the default x pointing east)
+
+use std::fs;
+use std::path::Path;
+use libm::{cos, sin};
+
+use crate::vector3::Vector3;
+use crate::constants;
+use crate::si_units as units;
+use crate::atmosphere::AtmosphereModel;
+use crate::aerodynamics::interpolate;
+use crate::psychrometrics::{self, SaturationCurve};
+use crate::parse::{self, ParseError};
+
+// Column width of the fixed width text format
+const TEXT_COLUMN_WIDTH: usize = 7;
+
+// One level of a sounding, None where the value is missing
+// Pressure in Pa, height above sea level in m, temperatures in K,
+// wind direction is the compass bearing it blows from (rad)
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct SoundingLevel {
+    pub pressure: Option<f64>,
+    pub height: f64,
+    pub temperature: Option<f64>,
+    pub dew_point: Option<f64>,
+    pub wind_direction: Option<f64>,
+    pub wind_speed: Option<f64>,
+    _private: (),
+}
+
+#[derive(Clone)]
+pub struct SoundingAtmosphere {
+    // Height of z = 0, the lowest level with a temperature unless changed
+    pub elevation: f64,
+    pub x_bearing: f64,
+    pub saturation_curve: SaturationCurve,
+    // (height, value) tables sorted by height
+    temperature: Vec<(f64, f64)>,
+    log_pressure: Vec<(f64, f64)>,
+    dew_point: Vec<(f64, f64)>,
+    wind_east: Vec<(f64, f64)>,
+    wind_north: Vec<(f64, f64)>,
+    _private: (),
+}
+
+impl SoundingLevel {
+    pub fn new(pressure: Option<f64>, height: f64, temperature: Option<f64>,
+        dew_point: Option<f64>, wind_direction: Option<f64>,
+        wind_speed: Option<f64>) -> SoundingLevel {
+        SoundingLevel{ pressure, height, temperature, dew_point,
+            wind_direction, wind_speed, _private: () }
+    }
+}
+
+impl SoundingAtmosphere {
+    // None unless at least one level reports pressure and one temperature
+    pub fn new(levels: &[SoundingLevel]) -> Option<SoundingAtmosphere> {
+        let mut levels = levels.to_vec();
+        levels.sort_by(|a, b| a.height.total_cmp(&b.height));
+        let table = |value: &dyn Fn(&SoundingLevel) -> Option<f64>| {
+            levels.iter()
+                .filter_map(|level| value(level).map(|v| (level.height, v)))
+                .collect::<Vec<(f64, f64)>>()
+        };
+
+        let temperature = table(&|level| level.temperature);
+        let log_pressure = table(&|level| level.pressure.map(f64::ln));
+        if temperature.is_empty() || log_pressure.is_empty() {
+            return None;
+        }
+        // Meteorological direction is where the wind comes from
+        let wind = |level: &SoundingLevel| {
+            Some((level.wind_direction?, level.wind_speed?))
+        };
+        let wind_east = table(&|level| wind(level)
+            .map(|(direction, speed)| -speed * sin(direction)));
+        let wind_north = table(&|level| wind(level)
+            .map(|(direction, speed)| -speed * cos(direction)));
+
+        Some(SoundingAtmosphere{
+            elevation: temperature[0].0,
+            x_bearing: 90. * units::DEGREES,
+            saturation_curve: SaturationCurve::default(),
+            temperature, log_pressure,
+            dew_point: table(&|level| level.dew_point),
+            wind_east, wind_north, _private: () })
+    }
+
+    // CSV with one level per line: pressure (hPa), height (m),
+    // temperature (C), dew point (C), wind direction (deg), wind speed (m/s)
+    // Empty fields are missing values
+    pub fn from_csv<P: AsRef<Path>>(path: P)
+        -> Result<SoundingAtmosphere, ParseError> {
+        let text = fs::read_to_string(path)?;
+        SoundingAtmosphere::from_csv_str(&text)
+    }
+
+    pub fn from_csv_str(text: &str) -> Result<SoundingAtmosphere, ParseError> {
+        let mut levels: Vec<SoundingLevel> = Vec::new();
+        for (line, content) in parse::data_lines(text) {
+            let fields = content.split(',')
+                .map(|field| optional_number(line, field))
+                .collect::<Result<Vec<Option<f64>>, ParseError>>()?;
+            if fields.len() != 6 {
+                return Err(ParseError::InvalidFormat{ line, message: format!(
+                    "expected 6 columns, found {}", fields.len()) });
+            }
+            levels.extend(level(line, &fields, units::M / units::S)?);
+        }
+        from_levels(text, &levels)
+    }
+
+    // Fixed width text listing as served by the University of Wyoming
+    // sounding archive, with the columns PRES (hPa), HGHT (m), TEMP (C),
+    // DWPT (C), DRCT (deg) and SKNT (knot) in any order among others
+    // https://weather.uwyo.edu/upperair/sounding.html
+    pub fn from_text<P: AsRef<Path>>(path: P)
+        -> Result<SoundingAtmosphere, ParseError> {
+        let text = fs::read_to_string(path)?;
+        SoundingAtmosphere::from_text_str(&text)
+    }
+
+    pub fn from_text_str(text: &str) -> Result<SoundingAtmosphere, ParseError> {
+        let mut lines = text.lines().enumerate();
+        let header = lines.by_ref().map(|(_, content)| content)
+            .find(|content| content.contains("PRES") && content.contains("HGHT"))
+            .ok_or_else(|| ParseError::InvalidFormat{ line: last_line(text),
+                message: "no PRES and HGHT header".to_string() })?;
+        // Columns are right aligned, find where each name ends
+        let column = |name: &str| {
+            header.find(name).map(|start| start + name.len())
+        };
+        let ends = ["PRES", "HGHT", "TEMP", "DWPT", "DRCT", "SKNT"].map(column);
+
+        let mut levels: Vec<SoundingLevel> = Vec::new();
+        let mut started = false;
+        for (i, content) in lines {
+            let line = i + 1;
+            let is_data = !content.trim().is_empty() && content.chars()
+                .all(|c| c.is_ascii_digit() || " .-".contains(c));
+            if !is_data || content.trim().chars().all(|c| c == '-') {
+                // Units and rules before the data, anything after it ends it
+                if started { break; } else { continue; }
+            }
+            started = true;
+            let fields = ends.map(|end| match end {
+                Some(end) => {
+                    let start = end.saturating_sub(TEXT_COLUMN_WIDTH);
+                    optional_number(line,
+                        content.get(start..end.min(content.len())).unwrap_or(""))
+                },
+                None => Ok(None),
+            });
+            let fields = fields.into_iter()
+                .collect::<Result<Vec<Option<f64>>, ParseError>>()?;
+            levels.extend(level(line, &fields, units::KNOT)?);
+        }
+        from_levels(text, &levels)
+    }
+
+    fn vapor_pressure_at(&self, altitude: f64) -> f64 {
+        if self.dew_point.is_empty() {
+            return 0.;
+        }
+        self.saturation_curve.pressure(interpolate(&self.dew_point, altitude))
+    }
+}
+
+// Whole file errors are reported on its last line
+fn last_line(text: &str) -> usize {
+    text.lines().count().max(1)
+}
+
+fn from_levels(text: &str, levels: &[SoundingLevel])
+    -> Result<SoundingAtmosphere, ParseError> {
+    SoundingAtmosphere::new(levels).ok_or_else(|| ParseError::InvalidFormat{
+        line: last_line(text),
+        message: "sounding needs pressure and temperature".to_string() })
+}
+
+// Field that may be empty
+fn optional_number(line: usize, field: &str) -> Result<Option<f64>, ParseError> {
+    if field.trim().is_empty() {
+        Ok(None)
+    } else {
+        parse::parse_number(line, field).map(Some)
+    }
+}
+
+// Level from pressure (hPa), height (m), temperature (C), dew point (C),
+// wind direction (deg) and wind speed in the given unit,
+// None without a height
+fn level(line: usize, fields: &[Option<f64>], speed_unit: f64)
+    -> Result<Option<SoundingLevel>, ParseError> {
+    let celsius = |value: Option<f64>| value.map(units::celsius_to_kelvin);
+    let Some(height) = fields[1] else {
+        return Ok(None);
+    };
+    if let Some(pressure) = fields[0] {
+        if pressure <= 0. {
+            return Err(ParseError::InvalidFormat{ line,
+                message: format!("pressure {} is not positive", pressure) });
+        }
+    }
+    Ok(Some(SoundingLevel::new(fields[0].map(|p| p * units::HPA),
+        height * units::M, celsius(fields[2]), celsius(fields[3]),
+        fields[4].map(|d| d * units::DEGREES), fields[5].map(|s| s * speed_unit))))
+}
+
+impl AtmosphereModel for SoundingAtmosphere {
+    fn reference_elevation(&self) -> f64 {
+        self.elevation
+    }
+
+    fn temperature_at(&self, altitude: f64) -> f64 {
+        interpolate(&self.temperature, altitude)
+    }
+
+    fn pressure_at(&self, altitude: f64) -> f64 {
+        interpolate(&self.log_pressure, altitude).exp()
+    }
+
+    fn density_at(&self, altitude: f64) -> f64 {
+        let pressure = self.pressure_at(altitude);
+        let vapor_pressure = self.vapor_pressure_at(altitude);
+        ((pressure - vapor_pressure) * constants::AIR_MOL_MASS
+            + vapor_pressure * constants::WATER_VAPOR_MOL_MASS)
+            / (constants::R * self.temperature_at(altitude))
+    }
+
+    fn speed_of_sound_at(&self, altitude: f64) -> f64 {
+        psychrometrics::speed_of_sound(self.temperature_at(altitude),
+            self.vapor_pressure_at(altitude), self.pressure_at(altitude))
+    }
+
+    fn wind(&self, position: Vector3, _time: f64) -> Vector3 {
+        let altitude = self.elevation + position.z;
+        let east = interpolate(&self.wind_east, altitude);
+        let north = interpolate(&self.wind_north, altitude);
+        // Rotate from east/north onto x/y
+        let (sin_b, cos_b) = (sin(self.x_bearing), cos(self.x_bearing));
+        Vector3::new(east * sin_b + north * cos_b,
+            -east * cos_b + north * sin_b, 0.)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Excerpt of a University of Wyoming listing, the two lowest levels
+    // are below the station and only have pressure and height
+    const UWYO: &str = "\
+72469 DNR Denver Observations at 00Z 01 Jan 2020
+
+-----------------------------------------------------------------------------
+   PRES   HGHT   TEMP   DWPT   RELH   MIXR   DRCT   SKNT   THTA   THTE   THTV
+    hPa      m      C      C      %   g/kg    deg   knot      K      K      K
+-----------------------------------------------------------------------------
+ 1000.0    115
+  925.0    780
+  838.0   1625    4.2   -9.8     36   2.17    250     10  289.2  295.9  289.6
+  835.0   1652    5.0  -12.0     28   1.80    254     11  290.3  296.0  290.6
+  800.0   1978    3.0  -13.0     30   1.69    265     17  291.6  297.0  291.9
+  700.0   3034   -4.9  -17.9     35   1.32    285     27  294.3  298.6  294.5
+Station information and sounding indices
+                         Station identifier: DNR
+";
+
+    #[test]
+    fn reads_the_wyoming_text_listing() {
+        let atm = SoundingAtmosphere::from_text_str(UWYO).unwrap();
+        assert_eq!(atm.elevation, 1625.);
+        assert!((atm.temperature_at(1625.) - 277.35).abs() < 1e-9);
+        assert!((atm.temperature_at(1978.) - 276.15).abs() < 1e-9);
+        assert!((atm.pressure_at(1625.) - 83_800.).abs() < 1e-6);
+        assert!((atm.pressure_at(115.) - 100_000.).abs() < 1e-6);
+        // 10 knots from 250 degrees blows towards the east north east,
+        // x points east by default
+        let wind = atm.wind(Vector3::new(0., 0., 0.), 0.);
+        let speed = 10. * units::KNOT;
+        let from = 250. * units::DEGREES;
+        assert!((wind.x + speed * sin(from)).abs() < 1e-9);
+        assert!((wind.y + speed * cos(from)).abs() < 1e-9);
+        assert!(wind.x > 0. && wind.y > 0.);
+
+        let no_data = &UWYO[..UWYO.find(" 1000.0").unwrap()];
+        assert!(matches!(SoundingAtmosphere::from_text_str(no_data),
+            Err(ParseError::InvalidFormat{ line: 6, .. })));
+        assert!(matches!(SoundingAtmosphere::from_text_str("no header\n"),
+            Err(ParseError::InvalidFormat{ line: 1, .. })));
+    }
+
+    #[test]
+    fn reads_csv_with_missing_values() {
+        let csv = "# hPa, m, C, C, deg, m/s\n\
+            1000, 115, , , , \n\
+            838, 1625, 4.2, -9.8, 250, 5.1\n\
+            700, 3034, -4.9, -17.9, 285, 13.9\n";
+        let atm = SoundingAtmosphere::from_csv_str(csv).unwrap();
+        assert_eq!(atm.elevation, 1625.);
+        assert!((atm.temperature_at(3034.) - 268.25).abs() < 1e-9);
+        // Held at the nearest level outside the data
+        assert!((atm.temperature_at(5000.) - 268.25).abs() < 1e-9);
+        assert!((atm.pressure_at(115.) - 100_000.).abs() < 1e-6);
+        let wind = atm.wind(Vector3::new(0., 0., 3034. - 1625.), 0.);
+        assert!((wind.mag() - 13.9).abs() < 1e-9);
+
+        let err = |text: &str| SoundingAtmosphere::from_csv_str(text).err().unwrap();
+        assert!(matches!(err("838, 1625, 4.2\n"),
+            ParseError::InvalidFormat{ line: 1, .. }));
+        assert!(matches!(err("# no temperatures\n1000, 115, , , , \n\n"),
+            ParseError::InvalidFormat{ line: 3, .. }));
+        assert!(matches!(err("1000, 115, , , , \n838, 1625, warm, , , \n"),
+            ParseError::InvalidNumber{ line: 2, .. }));
+        assert!(matches!(err("-5, 115, 4.2, , , \n"),
+            ParseError::InvalidFormat{ line: 1, .. }));
+    }
+}
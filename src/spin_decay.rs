@@ -0,0 +1,34 @@
+use crate::vector3::Vector3;
+
+// How the spin of a projectile evolves during flight
+// The spin axis is kept, only the spin rate decays
+#[derive(Copy, Clone, Default)]
+pub enum SpinDecay {
+    // Spin stays at its launch value
+    #[default]
+    Constant,
+    // d(omega)/dt = -omega / time_constant
+    Exponential { time_constant: f64 },
+    // Aerodynamic torque opposing the spin,
+    // |torque| = coefficient * rho * r^5 * omega^2,
+    // d(omega)/dt = -torque / moment_of_inertia
+    Torque { coefficient: f64, moment_of_inertia: f64 },
+}
+
+impl SpinDecay {
+    // Time derivative of the spin vector
+    pub fn spin_rate(&self, spin: Vector3, air_density: f64, radius: f64)
+        -> Vector3 {
+        match *self {
+            SpinDecay::Constant => Vector3::new(0., 0., 0.),
+            SpinDecay::Exponential { time_constant } => {
+                spin * (-1. / time_constant)
+            },
+            SpinDecay::Torque { coefficient, moment_of_inertia } => {
+                let torque_per_omega = coefficient * air_density
+                    * radius.powi(5) * spin.mag();
+                spin * (-torque_per_omega / moment_of_inertia)
+            },
+        }
+    }
+}
@@ -3,6 +3,7 @@ use num::complex::Complex;
 use ndarray::{Array1, Array2, arr1, arr2};
 use libm::{cos, acos, sin, atan2};
 
+#[allow(clippy::manual_non_exhaustive)]
 pub struct Spinor { // W: struct is never constructed: `Spinor`
     pub s1: Complex<f64>,
     pub s2: Complex<f64>,
@@ -31,37 +32,34 @@ impl Spinor {
         let norm_s1 = self.s1.norm();
         let norm_s2 = self.s2.norm();
         let norm = (norm_s1.powf(2.0) + norm_s2.powf(2.0)).sqrt();
-        self.s1 = self.s1 / norm;
-        self.s2 = self.s2 / norm;
+        self.s1 /= norm;
+        self.s2 /= norm;
         self.spinor = arr2(&[[self.s1],
                              [self.s2]]);
         self.spinor_transpose = arr1(&[self.s1, self.s2]);
     }
 
     pub fn conj(&self) -> Spinor {
-        let spinor_conj = Spinor::new(self.s1.conj(), self.s2.conj());
-        spinor_conj
+        Spinor::new(self.s1.conj(), self.s2.conj())
     }
 
     pub fn rotate_phase(&mut self, phase:Complex<f64>) {
        let exp_phase = Complex::exp(phase);
-       self.s1 = self.s1 * exp_phase;
-       self.s2 = self.s2 * exp_phase;
+       self.s1 *= exp_phase;
+       self.s2 *= exp_phase;
     }
 
     pub fn get_phi(&self) -> f64 {
         let r_y = (Complex::new(0., 1.) *
                     (self.s1 * self.s2.conj() - self.s2 * self.s1.conj())).re;
         let r_z = self.s1.norm_sqr() - self.s2.norm_sqr();
-        let phi = atan2(r_y, r_z);
-
-        phi
+        atan2(r_y, r_z)
     }
 
     pub fn get_alpha(&self) -> f64 {
         let phi = self.get_phi();
         let mut alpha = -2.0 * (self.s1 - Complex::new(phi, 0.)).arg();
-        alpha = alpha % (2. * PI);
+        alpha %= 2. * PI;
       	if alpha.abs() < 0.00001 { alpha = 0.; }
       	if alpha > 2. * PI - 0.0001 { alpha = 0.; }
 
@@ -71,9 +69,7 @@ impl Spinor {
     pub fn get_theta(&self) ->f64 {
         let r = self.s1.norm_sqr() + self.s2.norm_sqr();
         let r_z = self.s1.norm_sqr() - self.s2.norm_sqr();
-        let theta = acos(r_z / r);
-
-        theta
+        acos(r_z / r)
     }
 
     pub fn construct_spinor_flag(&mut self, flag_length: f64, flag_width: f64) -> Vec<(f64, f64, f64)> {
@@ -116,18 +112,19 @@ impl Spinor {
         let flag_vec = flag_length * rot_mat.dot(&perp_vec);
    
         // Create output of vector of 3D coordinates for plotting
-        let mut unit_flag_coords:Vec<(f64, f64, f64)> = vec![];
-        unit_flag_coords.push((0., 0., 0.)); // Base
-        unit_flag_coords.push((r_x, r_y, r_z)); // Top
-        unit_flag_coords.push((r_x + flag_vec[0],
-                               r_y + flag_vec[1],
-                               r_z + flag_vec[2])); // Top corner of flag
-        unit_flag_coords.push(((1. - flag_width) * r_x + flag_vec[0],
-                               (1. - flag_width) * r_y + flag_vec[1],
-                               (1. - flag_width) * r_z + flag_vec[2])); // bottom corner of flag
-        unit_flag_coords.push(((1. - flag_width) * r_x,
-                               (1. - flag_width) * r_y,
-                               (1. - flag_width) * r_z)); // Bottom corner of flag, on pole
+        let unit_flag_coords:Vec<(f64, f64, f64)> = vec![
+            (0., 0., 0.), // Base
+            (r_x, r_y, r_z), // Top
+            (r_x + flag_vec[0],
+             r_y + flag_vec[1],
+             r_z + flag_vec[2]), // Top corner of flag
+            ((1. - flag_width) * r_x + flag_vec[0],
+             (1. - flag_width) * r_y + flag_vec[1],
+             (1. - flag_width) * r_z + flag_vec[2]), // bottom corner of flag
+            ((1. - flag_width) * r_x,
+             (1. - flag_width) * r_y,
+             (1. - flag_width) * r_z), // Bottom corner of flag, on pole
+        ];
    
         unit_flag_coords
     }
@@ -0,0 +1,228 @@
+// U.S. Standard Atmosphere 1976 (identical to the ICAO standard
+// atmosphere below 32 km), layered model up to 86 km geometric altitude
+// https://ntrs.nasa.gov/citations/19770009539
+//
+// The layers are defined in geopotential altitude, the AtmosphereModel
+// methods take geometric altitude. Altitudes outside -5 to 86 km are
+// clamped to the nearest end of the model, the troposphere lapse rate
+// is continued below sea level as in the published tables
+// Temperatures are molecular-scale temperatures, which are up to 0.08 K
+// above the kinetic temperature between 80 and 86 km
+
+use libm::exp;
+
+use std::sync::Arc;
+
+use crate::vector3::Vector3;
+use crate::wind::WindField;
+use crate::constants;
+use crate::si_units as units;
+use crate::atmosphere::AtmosphereModel;
+use crate::root_finding;
+
+// Constants as given in the standard, which differ slightly from the
+// current CODATA values in crate::constants
+const GAS_CONSTANT: f64 = 8.314_32 * units::J / (units::MOL * units::K);
+const EARTH_RADIUS: f64 = 6_356_766. * units::M;
+const SEA_LEVEL_TEMPERATURE: f64 = 288.15 * units::K;
+// Sutherland's law coefficients used by the standard
+const SUTHERLAND_BETA: f64 = 1.458e-6;
+const SUTHERLAND_TEMPERATURE: f64 = 110.4 * units::K;
+
+// Lowest geopotential altitude of the model (m)
+const MIN_ALTITUDE: f64 = -5_000. * units::M;
+const ALTITUDE_TOL: f64 = 1e-6 * units::M;
+const MAX_ITER: usize = 100;
+
+// Base geopotential altitude (m) and temperature lapse rate (K/m)
+// of each layer, the last entry is the top of the model
+const LAYERS: [(f64, f64); 8] = [
+    (0., -6.5e-3),
+    (11_000., 0.),
+    (20_000., 1.0e-3),
+    (32_000., 2.8e-3),
+    (47_000., 0.),
+    (51_000., -2.8e-3),
+    (71_000., -2.0e-3),
+    (84_852., 0.),
+];
+
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Clone)]
+pub struct StandardAtmosphere {
+    pub elevation: f64,
+    pub wind: Vector3,
+    // Position and time dependent wind, used instead of wind when set
+    pub wind_field: Option<Arc<dyn WindField>>,
+    _private: (),
+}
+
+impl StandardAtmosphere {
+    pub fn new(elevation: f64, wind: Vector3) -> StandardAtmosphere {
+        StandardAtmosphere{ elevation, wind, wind_field: None, _private: () }
+    }
+
+    // Temperature and pressure at a geopotential altitude
+    pub fn temperature_pressure(&self, geopotential: f64) -> (f64, f64) {
+        temperature_pressure(geopotential)
+    }
+}
+
+fn temperature_pressure(geopotential: f64) -> (f64, f64) {
+    let top = LAYERS[LAYERS.len() - 1].0;
+    let h = geopotential.clamp(MIN_ALTITUDE, top);
+    let exponent_scale = constants::G * constants::AIR_MOL_MASS / GAS_CONSTANT;
+
+    // Walk up through the layers below h
+    let mut temperature = SEA_LEVEL_TEMPERATURE;
+    let mut pressure = constants::PRESSURE_SEA_LEVEL;
+    for window in LAYERS.windows(2) {
+        let (base, lapse) = window[0];
+        let thickness = h.min(window[1].0) - base;
+        let top_temperature = temperature + lapse * thickness;
+        pressure *= if lapse == 0. {
+            exp(-exponent_scale * thickness / temperature)
+        } else {
+            (temperature / top_temperature).powf(exponent_scale / lapse)
+        };
+        temperature = top_temperature;
+        if h <= window[1].0 {
+            break;
+        }
+    }
+    (temperature, pressure)
+}
+
+// Geopotential altitude where the standard atmosphere has the given
+// pressure, None outside the model
+pub fn pressure_altitude(pressure: f64) -> Option<f64> {
+    standard_altitude(|h| {
+        let (_, standard_pressure) = temperature_pressure(h);
+        standard_pressure.ln() - pressure.ln()
+    })
+}
+
+// Geopotential altitude where the standard atmosphere has the given
+// density, None outside the model
+pub fn density_altitude(density: f64) -> Option<f64> {
+    standard_altitude(|h| {
+        let (temperature, pressure) = temperature_pressure(h);
+        let standard_density = pressure * constants::AIR_MOL_MASS
+            / (GAS_CONSTANT * temperature);
+        standard_density.ln() - density.ln()
+    })
+}
+
+// Root of a function of geopotential altitude over the model
+fn standard_altitude<F: Fn(f64) -> f64>(f: F) -> Option<f64> {
+    let top = LAYERS[LAYERS.len() - 1].0;
+    root_finding::brent(f, MIN_ALTITUDE, top, ALTITUDE_TOL, MAX_ITER)
+}
+
+// Station pressure from an altimeter setting (QNH) and the station's
+// elevation, inverting the standard troposphere
+// https://www.weather.gov/media/epz/wxcalc/stationPressure.pdf
+pub fn station_pressure(altimeter_setting: f64, elevation: f64) -> f64 {
+    let lapse = -LAYERS[0].1;
+    let n = GAS_CONSTANT * lapse / (constants::G * constants::AIR_MOL_MASS);
+    (altimeter_setting.powf(n) - constants::PRESSURE_SEA_LEVEL.powf(n)
+        * lapse * elevation / SEA_LEVEL_TEMPERATURE).powf(1. / n)
+}
+
+// Geopotential altitude of a geometric altitude
+pub fn geopotential_altitude(geometric: f64) -> f64 {
+    EARTH_RADIUS * geometric / (EARTH_RADIUS + geometric)
+}
+
+// Geometric altitude of a geopotential altitude
+pub fn geometric_altitude(geopotential: f64) -> f64 {
+    EARTH_RADIUS * geopotential / (EARTH_RADIUS - geopotential)
+}
+
+impl AtmosphereModel for StandardAtmosphere {
+    fn reference_elevation(&self) -> f64 {
+        self.elevation
+    }
+
+    fn temperature_at(&self, altitude: f64) -> f64 {
+        self.temperature_pressure(geopotential_altitude(altitude)).0
+    }
+
+    fn pressure_at(&self, altitude: f64) -> f64 {
+        self.temperature_pressure(geopotential_altitude(altitude)).1
+    }
+
+    fn density_at(&self, altitude: f64) -> f64 {
+        let (temperature, pressure) =
+            self.temperature_pressure(geopotential_altitude(altitude));
+        pressure * constants::AIR_MOL_MASS / (GAS_CONSTANT * temperature)
+    }
+
+    fn dynamic_viscosity_at(&self, altitude: f64) -> f64 {
+        let temperature = self.temperature_at(altitude);
+        SUTHERLAND_BETA * temperature.powf(1.5)
+            / (temperature + SUTHERLAND_TEMPERATURE) * units::PA * units::S
+    }
+
+    fn speed_of_sound_at(&self, altitude: f64) -> f64 {
+        (constants::AIR_ADIABATIC_INDEX * GAS_CONSTANT
+            * self.temperature_at(altitude) / constants::AIR_MOL_MASS).sqrt()
+    }
+
+    fn wind(&self, position: Vector3, time: f64) -> Vector3 {
+        match &self.wind_field {
+            Some(field) => field.velocity(position, time),
+            None => self.wind,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Geopotential altitude (m), temperature (K), pressure (Pa) and
+    // density (kg/m^3) from the tables of the standard
+    const TABLE: [(f64, f64, f64, f64); 9] = [
+        (-2_000., 301.15, 127_774., 1.4781),
+        (0., 288.15, 101_325., 1.2250),
+        (11_000., 216.65, 22_632.1, 0.36392),
+        (20_000., 216.65, 5_474.89, 0.088035),
+        (32_000., 228.65, 868.019, 0.013225),
+        (47_000., 270.65, 110.906, 1.4275e-3),
+        (51_000., 270.65, 66.9389, 8.6160e-4),
+        (71_000., 214.65, 3.95642, 6.4211e-5),
+        (84_852., 186.946, 0.37338, 6.958e-6),
+    ];
+
+    fn assert_relative(value: f64, expected: f64, tol: f64) {
+        assert!(((value - expected) / expected).abs() < tol,
+            "{} differs from {}", value, expected);
+    }
+
+    #[test]
+    fn matches_published_tables() {
+        let atm = StandardAtmosphere::new(0., Vector3::new(0., 0., 0.));
+        for (geopotential, temperature, pressure, density) in TABLE {
+            let altitude = geometric_altitude(geopotential);
+            assert!((atm.temperature_at(altitude) - temperature).abs() < 1e-3);
+            assert_relative(atm.pressure_at(altitude), pressure, 1e-4);
+            assert_relative(atm.density_at(altitude), density, 1e-4);
+        }
+    }
+
+    #[test]
+    fn pressure_and_density_altitudes_round_trip() {
+        let atm = StandardAtmosphere::new(0., Vector3::new(0., 0., 0.));
+        for (geopotential, _, _, _) in TABLE {
+            let altitude = geometric_altitude(geopotential);
+            let from_pressure = pressure_altitude(atm.pressure_at(altitude));
+            let from_density = density_altitude(atm.density_at(altitude));
+            assert!((from_pressure.unwrap() - geopotential).abs() < 1e-3);
+            assert!((from_density.unwrap() - geopotential).abs() < 1e-3);
+        }
+        assert!(pressure_altitude(1e6).is_none());
+        assert!(density_altitude(1e-9).is_none());
+    }
+}
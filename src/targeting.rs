@@ -0,0 +1,265 @@
+// Inverse ballistics, launch parameters that take a projectile
+// from its current position through a target point
+//
+// A shot is scored where its horizontal distance along the line to the
+// target first equals the target distance: the height miss is the
+// trajectory height there minus the target height, the lateral miss is
+// the sideways offset from that line (positive to the left)
+// The spin vector keeps its orientation relative to the launch azimuth
+
+use libm::{cos, sin, atan2};
+
+use crate::vector3::Vector3;
+use crate::si_units as units;
+use crate::atmosphere::AtmosphereModel;
+use crate::integrators::Integrator;
+use crate::events::{Direction, Event};
+use crate::projectiles::Projectile;
+use crate::root_finding;
+
+// Long enough for any shot that can reach its target (s)
+const MAX_FLIGHT_TIME: f64 = 120.;
+// Elevation scan used to bracket the low and high solutions
+const ANGLE_SCAN_MIN: f64 = -85. * units::DEGREES;
+const ANGLE_SCAN_MAX: f64 = 89.5 * units::DEGREES;
+const ANGLE_SCAN_STEP: f64 = 1. * units::DEGREES;
+// Largest launch speed tried when searching for the minimum speed (m/s)
+const MAX_SPEED: f64 = 1e4 * units::M / units::S;
+// Furthest the azimuth is turned away from the target line
+const MAX_AZIMUTH_CORRECTION: f64 = 45. * units::DEGREES;
+
+const ANGLE_TOL: f64 = 1e-8;
+const SPEED_TOL: f64 = 1e-8;
+const MAX_ITER: usize = 100;
+
+// Height and lateral miss at the target distance
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct Miss {
+    pub height: f64,
+    pub lateral: f64,
+    _private: (),
+}
+
+impl Miss {
+    pub fn new(height: f64, lateral: f64) -> Miss {
+        Miss{ height, lateral, _private: () }
+    }
+}
+
+impl Projectile {
+    // Miss at the target when launched with the given speed and angles,
+    // None if the projectile never gets as far as the target
+    pub fn miss(
+        &self, atm: &dyn AtmosphereModel, integrator: &dyn Integrator,
+        t_step: f64, launch: (f64, f64, f64), target: Vector3) -> Option<Miss> {
+
+        let (speed, theta, phi) = launch;
+        let start = self.position;
+        let to_target = Vector3::new(target.x - start.x, target.y - start.y, 0.);
+        let distance = to_target.mag();
+        if distance == 0. {
+            return None;
+        }
+        let bearing = to_target.normalize();
+
+        let mut shot = self.clone();
+        shot.spin = rotate_z(self.spin, phi - self.get_phi());
+        shot.set_speed_theta_phi(speed, theta, phi);
+
+        let reached = Event::new(move |_t, state| {
+            (state.position - start).dot(bearing) - distance
+        }, Direction::Rising, true);
+        // No ground, the shot keeps falling until it gets there
+        let result = shot.trajectory_with_events(atm, integrator, t_step,
+            f64::NEG_INFINITY, MAX_FLIGHT_TIME, &[reached]);
+
+        let (_, hit) = result.events.first()?;
+        let delta = hit.position - start;
+        Some(Miss::new(hit.position.z - target.z,
+            Vector3::cross_prod(&bearing, &delta).z))
+    }
+
+    // Low and high elevation angles that hit the target at the given speed,
+    // launched straight at it, None where there is no solution
+    pub fn launch_angles(
+        &self, atm: &dyn AtmosphereModel, integrator: &dyn Integrator,
+        t_step: f64, speed: f64, target: Vector3) -> (Option<f64>, Option<f64>) {
+
+        let phi = self.target_azimuth(target);
+        let height_miss = |theta: f64| {
+            self.miss(atm, integrator, t_step, (speed, theta, phi), target)
+                .map_or(f64::NEG_INFINITY, |miss| miss.height)
+        };
+
+        // Bracket sign changes of the height miss over the elevation scan
+        let mut roots: Vec<f64> = Vec::new();
+        let mut theta0 = ANGLE_SCAN_MIN;
+        let mut miss0 = height_miss(theta0);
+        while theta0 < ANGLE_SCAN_MAX && roots.len() < 2 {
+            let theta1 = (theta0 + ANGLE_SCAN_STEP).min(ANGLE_SCAN_MAX);
+            let miss1 = height_miss(theta1);
+            if miss0.is_finite() && miss1.is_finite() && miss0 * miss1 <= 0. {
+                if let Some(root) = root_finding::brent(height_miss,
+                    theta0, theta1, ANGLE_TOL, MAX_ITER) {
+                    roots.push(root);
+                }
+            }
+            theta0 = theta1;
+            miss0 = miss1;
+        }
+
+        (roots.first().copied(), roots.get(1).copied())
+    }
+
+    // Smallest launch speed at elevation theta that reaches the target,
+    // launched straight at it
+    pub fn minimum_speed(
+        &self, atm: &dyn AtmosphereModel, integrator: &dyn Integrator,
+        t_step: f64, theta: f64, target: Vector3) -> Option<f64> {
+
+        let phi = self.target_azimuth(target);
+        let height_miss = |speed: f64| {
+            self.miss(atm, integrator, t_step, (speed, theta, phi), target)
+                .map_or(f64::NEG_INFINITY, |miss| miss.height)
+        };
+
+        // Double the speed until the shot passes over the target
+        let mut low = 0.;
+        let mut high = 1. * units::M / units::S;
+        while height_miss(high) < 0. {
+            low = high;
+            high *= 2.;
+            if high > MAX_SPEED {
+                return None;
+            }
+        }
+        // Shots too slow to get there count as falling infinitely short
+        let finite_miss = |speed: f64| {
+            let miss = height_miss(speed);
+            if miss.is_finite() { miss } else { -f64::MAX }
+        };
+        root_finding::brent(finite_miss, low, high, SPEED_TOL, MAX_ITER)
+    }
+
+    // Azimuth that cancels the lateral miss from crosswind and Magnus
+    // drift, at the projectile's current launch speed and elevation
+    pub fn compensating_azimuth(
+        &self, atm: &dyn AtmosphereModel, integrator: &dyn Integrator,
+        t_step: f64, target: Vector3) -> Option<f64> {
+
+        let speed = self.get_speed();
+        let theta = self.get_theta();
+        let lateral_miss = |phi: f64| {
+            self.miss(atm, integrator, t_step, (speed, theta, phi), target)
+                .map_or(f64::NAN, |miss| miss.lateral)
+        };
+
+        let phi = self.target_azimuth(target);
+        root_finding::brent(lateral_miss, phi - MAX_AZIMUTH_CORRECTION,
+            phi + MAX_AZIMUTH_CORRECTION, ANGLE_TOL, MAX_ITER)
+    }
+
+    // Azimuth of the straight line to the target
+    pub fn target_azimuth(&self, target: Vector3) -> f64 {
+        atan2(target.y - self.position.y, target.x - self.position.x)
+    }
+}
+
+// Rotate a vector about the z axis
+fn rotate_z(vec: Vector3, angle: f64) -> Vector3 {
+    Vector3::new(vec.x * cos(angle) - vec.y * sin(angle),
+        vec.x * sin(angle) + vec.y * cos(angle), vec.z)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_4;
+    use crate::constants;
+    use crate::standard_atmosphere::StandardAtmosphere;
+    use crate::integrators::RungeKutta4;
+
+    const T_STEP: f64 = 0.01;
+
+    // No drag or Magnus force, so the flight is the vacuum parabola
+    fn drag_free() -> Projectile {
+        let mut ball = Projectile::new();
+        ball.mass = 0.1;
+        ball.radius = 0.03;
+        ball
+    }
+
+    fn calm() -> StandardAtmosphere {
+        StandardAtmosphere::new(0., Vector3::new(0., 0., 0.))
+    }
+
+    #[test]
+    fn launch_angles_match_the_vacuum_parabola() {
+        let g = constants::G;
+        let speed = 30.;
+        let ball = drag_free();
+
+        // Level target, sin(2 theta) = g R / v^2 and the two angles
+        // are symmetric about 45 degrees
+        let range = 60.;
+        let (low, high) = ball.launch_angles(&calm(), &RungeKutta4, T_STEP,
+            speed, Vector3::new(0., range, 0.));
+        let (low, high) = (low.unwrap(), high.unwrap());
+        let expected = (g * range / speed.powi(2)).asin() / 2.;
+        assert!((low - expected).abs() < 1e-6);
+        assert!((low + high - 2. * FRAC_PI_4).abs() < 1e-6);
+
+        // Raised target, tan(theta) = (v^2 -+ sqrt(v^4 - g (g x^2 + 2 y v^2))) / (g x)
+        let (x, y) = (50., 10.);
+        let (low, high) = ball.launch_angles(&calm(), &RungeKutta4, T_STEP,
+            speed, Vector3::new(x, 0., y));
+        let disc = (speed.powi(4) - g * (g * x.powi(2) + 2. * y * speed.powi(2))).sqrt();
+        assert!((low.unwrap() - ((speed.powi(2) - disc) / (g * x)).atan()).abs() < 1e-6);
+        assert!((high.unwrap() - ((speed.powi(2) + disc) / (g * x)).atan()).abs() < 1e-6);
+
+        // Out of range
+        let out_of_reach = 1.1 * speed.powi(2) / g;
+        assert_eq!(ball.launch_angles(&calm(), &RungeKutta4, T_STEP, speed,
+            Vector3::new(out_of_reach, 0., 0.)), (None, None));
+    }
+
+    #[test]
+    fn minimum_speed_matches_the_vacuum_parabola() {
+        let g = constants::G;
+        let range = 80.;
+        let ball = drag_free();
+        let speed = ball.minimum_speed(&calm(), &RungeKutta4, T_STEP,
+            FRAC_PI_4, Vector3::new(range, 0., 0.)).unwrap();
+        assert!((speed - (g * range).sqrt()).abs() < 1e-6);
+        // Steeper than 45 degrees needs more speed, v^2 = g R / sin(2 theta)
+        let theta = 60. * units::DEGREES;
+        let speed = ball.minimum_speed(&calm(), &RungeKutta4, T_STEP,
+            theta, Vector3::new(range, 0., 0.)).unwrap();
+        assert!((speed - (g * range / (2. * theta).sin()).sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn compensating_azimuth_aims_into_the_crosswind() {
+        let target = Vector3::new(30., 40., 0.);
+        let mut ball = drag_free();
+        ball.set_speed_theta_phi(30., 0.3, 0.);
+        // Nothing pushes the ball sideways without drag
+        let phi = ball.compensating_azimuth(&calm(), &RungeKutta4, T_STEP,
+            target).unwrap();
+        assert!((phi - ball.target_azimuth(target)).abs() < 1e-6);
+
+        // Wind blowing towards -x drifts a shot along y to the left, so
+        // the aim turns right, towards the wind
+        let windy = StandardAtmosphere::new(0., Vector3::new(-5., 0., 0.));
+        ball.drag_coefficient = 0.5;
+        let target = Vector3::new(0., 40., 0.);
+        let phi = ball.compensating_azimuth(&windy, &RungeKutta4, T_STEP,
+            target).unwrap();
+        assert!(phi < ball.target_azimuth(target));
+        let miss = ball.miss(&windy, &RungeKutta4, T_STEP,
+            (30., 0.3, phi), target).unwrap();
+        assert!(miss.lateral.abs() < 1e-6);
+    }
+}
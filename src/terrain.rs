@@ -0,0 +1,249 @@
+use std::fs;
+use std::path::Path;
+use ndarray::Array2;
+
+use crate::vector3::Vector3;
+use crate::parse::{self, ParseError};
+
+// Ground height z(x, y) that trajectories land on
+pub trait Terrain: Send + Sync {
+    fn height(&self, x: f64, y: f64) -> f64;
+
+    // Upward unit normal of the ground
+    fn normal(&self, x: f64, y: f64) -> Vector3;
+}
+
+
+/***********
+************
+Flat Ground
+************
+***********/
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct FlatTerrain {
+    pub height: f64,
+    _private: (),
+}
+
+impl FlatTerrain {
+    pub fn new(height: f64) -> FlatTerrain {
+        FlatTerrain{ height, _private: () }
+    }
+}
+
+impl Terrain for FlatTerrain {
+    fn height(&self, _x: f64, _y: f64) -> f64 {
+        self.height
+    }
+
+    fn normal(&self, _x: f64, _y: f64) -> Vector3 {
+        Vector3::new(0., 0., 1.)
+    }
+}
+
+
+/*************
+**************
+Inclined Plane
+**************
+*************/
+
+// z = height + slope_x * x + slope_y * y
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct InclinedPlane {
+    pub height: f64,
+    pub slope_x: f64,
+    pub slope_y: f64,
+    _private: (),
+}
+
+impl InclinedPlane {
+    pub fn new(height: f64, slope_x: f64, slope_y: f64) -> InclinedPlane {
+        InclinedPlane{ height, slope_x, slope_y, _private: () }
+    }
+}
+
+impl Terrain for InclinedPlane {
+    fn height(&self, x: f64, y: f64) -> f64 {
+        self.height + self.slope_x * x + self.slope_y * y
+    }
+
+    fn normal(&self, _x: f64, _y: f64) -> Vector3 {
+        Vector3::new(-self.slope_x, -self.slope_y, 1.).normalize()
+    }
+}
+
+
+/*********
+**********
+Height Map
+**********
+*********/
+
+// Regular grid of heights, heights[[i, j]] is at
+// x = origin_x + j * spacing_x, y = origin_y + i * spacing_y
+// Bilinear interpolation inside the grid, clamped to the edge outside
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Clone)]
+pub struct HeightMap {
+    pub heights: Array2<f64>,
+    pub origin_x: f64,
+    pub origin_y: f64,
+    pub spacing_x: f64,
+    pub spacing_y: f64,
+    _private: (),
+}
+
+impl HeightMap {
+    // Panics unless the grid is at least 2 x 2 and both spacings are
+    // finite and positive
+    pub fn new(heights: Array2<f64>, origin_x: f64, origin_y: f64,
+        spacing_x: f64, spacing_y: f64) -> HeightMap {
+        let (n_rows, n_cols) = heights.dim();
+        assert!(n_rows >= 2 && n_cols >= 2,
+            "height map must be at least 2 x 2, got {} x {}", n_rows, n_cols);
+        for spacing in [spacing_x, spacing_y] {
+            assert!(spacing.is_finite() && spacing > 0.,
+                "height map spacing must be finite and positive, got {}", spacing);
+        }
+        HeightMap{ heights, origin_x, origin_y,
+            spacing_x, spacing_y, _private: () }
+    }
+
+    // CSV with one grid row (constant y) per line, at least 2 rows of
+    // 2 heights, spacings that new would reject are an InvalidFormat error
+    pub fn from_csv<P: AsRef<Path>>(path: P, origin_x: f64, origin_y: f64,
+        spacing_x: f64, spacing_y: f64) -> Result<HeightMap, ParseError> {
+        let text = fs::read_to_string(path)?;
+        HeightMap::from_csv_str(&text, origin_x, origin_y, spacing_x, spacing_y)
+    }
+
+    pub fn from_csv_str(text: &str, origin_x: f64, origin_y: f64,
+        spacing_x: f64, spacing_y: f64) -> Result<HeightMap, ParseError> {
+        // The spacings are not in the file, blame its first data line
+        for spacing in [spacing_x, spacing_y] {
+            if !(spacing.is_finite() && spacing > 0.) {
+                let line = parse::data_lines(text).next().map_or(1, |(line, _)| line);
+                return Err(ParseError::InvalidFormat{ line, message: format!(
+                    "height map spacing must be finite and positive, got {}",
+                    spacing) });
+            }
+        }
+        let rows = parse::csv_numbers(text)?;
+        let n_cols = rows.first().map_or(0, |row| row.len());
+        if rows.len() < 2 || n_cols < 2 {
+            return Err(ParseError::InvalidFormat{ line: text.lines().count(),
+                message: format!("height map must be at least 2 x 2, got {} x {}",
+                    rows.len(), n_cols) });
+        }
+        let heights = Array2::from_shape_vec((rows.len(), n_cols),
+            rows.into_iter().flatten().collect())
+            .map_err(|err| ParseError::InvalidFormat{ line: text.lines().count(),
+                message: err.to_string() })?;
+        Ok(HeightMap::new(heights, origin_x, origin_y, spacing_x, spacing_y))
+    }
+
+    // Grid cell containing (x, y) and the fractional position inside it
+    fn cell(&self, x: f64, y: f64) -> (usize, usize, f64, f64) {
+        let (n_rows, n_cols) = self.heights.dim();
+        let locate = |coord: f64, n: usize| {
+            let max = (n - 1) as f64;
+            let u = coord.clamp(0., max);
+            let i = (u.floor() as usize).min(n - 2);
+            (i, u - i as f64)
+        };
+        let (j, fx) = locate((x - self.origin_x) / self.spacing_x, n_cols);
+        let (i, fy) = locate((y - self.origin_y) / self.spacing_y, n_rows);
+        (i, j, fx, fy)
+    }
+
+    // Heights at the four corners of the cell
+    fn corners(&self, i: usize, j: usize) -> (f64, f64, f64, f64) {
+        (self.heights[[i, j]], self.heights[[i, j + 1]],
+            self.heights[[i + 1, j]], self.heights[[i + 1, j + 1]])
+    }
+}
+
+impl Terrain for HeightMap {
+    fn height(&self, x: f64, y: f64) -> f64 {
+        let (i, j, fx, fy) = self.cell(x, y);
+        let (h00, h01, h10, h11) = self.corners(i, j);
+        h00 * (1. - fx) * (1. - fy) + h01 * fx * (1. - fy)
+            + h10 * (1. - fx) * fy + h11 * fx * fy
+    }
+
+    fn normal(&self, x: f64, y: f64) -> Vector3 {
+        let (i, j, fx, fy) = self.cell(x, y);
+        let (h00, h01, h10, h11) = self.corners(i, j);
+        // Gradient of the bilinear patch
+        let dhdx = ((h01 - h00) * (1. - fy) + (h11 - h10) * fy) / self.spacing_x;
+        let dhdy = ((h10 - h00) * (1. - fx) + (h11 - h01) * fx) / self.spacing_y;
+        Vector3::new(-dhdx, -dhdy, 1.).normalize()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 3 x 3 grid at 10 m spacing rising 1 m per 10 m in x and 2 m per
+    // 10 m in y, with a 2 m bump in the middle
+    const GRID: &str = "# heights (m)\n\
+        0, 1, 2\n\
+        2, 7, 4\n\
+        \n\
+        4, 5, 6\n";
+
+    fn grid() -> HeightMap {
+        HeightMap::from_csv_str(GRID, 100., 200., 10., 10.).unwrap()
+    }
+
+    #[test]
+    fn heights_are_bilinear_between_grid_points() {
+        let map = grid();
+        assert_eq!(map.heights.dim(), (3, 3));
+        assert!((map.height(100., 200.) - 0.).abs() < 1e-12);
+        assert!((map.height(110., 210.) - 7.).abs() < 1e-12);
+        assert!((map.height(120., 220.) - 6.).abs() < 1e-12);
+        // Cell centre is the mean of its corners, edges are linear
+        assert!((map.height(105., 205.) - 2.5).abs() < 1e-12);
+        assert!((map.height(115., 200.) - 1.5).abs() < 1e-12);
+        // Clamped to the edge outside the grid
+        assert!((map.height(50., 200.) - 0.).abs() < 1e-12);
+        assert!((map.height(200., 250.) - 6.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn normal_follows_the_patch_gradient() {
+        let map = grid();
+        // Along the y = 200 edge of the first cell dh/dx = 0.1, and at
+        // x = 100 dh/dy = 0.2
+        let n = map.normal(100., 200.);
+        let expected = Vector3::new(-0.1, -0.2, 1.).normalize();
+        assert!((n - expected).mag() < 1e-12);
+        assert!((n.mag() - 1.).abs() < 1e-12);
+        let n = map.normal(105., 205.);
+        let expected = Vector3::new(-0.3, -0.4, 1.).normalize();
+        assert!((n - expected).mag() < 1e-12);
+    }
+
+    #[test]
+    fn bad_height_maps_are_parse_errors() {
+        let err = |text: &str, spacing_x: f64| {
+            HeightMap::from_csv_str(text, 0., 0., spacing_x, 1.).err().unwrap()
+        };
+        assert!(matches!(err(GRID, 0.),
+            ParseError::InvalidFormat{ line: 2, .. }));
+        assert!(matches!(err(GRID, f64::NAN),
+            ParseError::InvalidFormat{ line: 2, .. }));
+        assert!(matches!(err("0, 1\n2, x\n", 1.),
+            ParseError::InvalidNumber{ line: 2, .. }));
+        assert!(matches!(err("0, 1\n2, 3, 4\n", 1.),
+            ParseError::InvalidFormat{ line: 2, .. }));
+        assert!(matches!(err("0, 1\n", 1.),
+            ParseError::InvalidFormat{ line: 1, .. }));
+    }
+}
@@ -0,0 +1,150 @@
+use libm::atan2;
+
+use crate::vector3::Vector3;
+use crate::events::EventHit;
+
+// Breakdown of the forces acting on a projectile
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct Forces {
+    pub drag: Vector3,
+    pub magnus: Vector3,
+    pub gravity: Vector3,
+    // Inertial forces from Earth's rotation, zero without an EarthFrame
+    pub coriolis: Vector3,
+    pub centrifugal: Vector3,
+    _private: (),
+}
+
+// Projectile state and derived quantities at one point in time
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct TrajectorySample {
+    pub time: f64,
+    pub position: Vector3,
+    pub velocity: Vector3,
+    pub spin: Vector3,
+    pub forces: Forces,
+    pub mach: f64,
+    pub reynolds: f64,
+    _private: (),
+}
+
+// Output of Projectile::trajectory
+#[allow(clippy::manual_non_exhaustive)]
+pub struct TrajectoryResult {
+    pub samples: Vec<TrajectorySample>,
+    // Sum of the local error estimates over all steps,
+    // None if the integrator does not estimate its error
+    pub error_estimate: Option<f64>,
+    // Where the projectile met the ground, None if max_time came first
+    pub landing: Option<EventHit>,
+    pub apex: Option<EventHit>,
+    // User events in the order they occurred, with their index
+    // into the events passed to the trajectory
+    pub events: Vec<(usize, EventHit)>,
+    _private: (),
+}
+
+impl Forces {
+    pub fn new(drag: Vector3, magnus: Vector3, gravity: Vector3) -> Forces {
+        let zero = Vector3::new(0., 0., 0.);
+        Forces{ drag, magnus, gravity, coriolis: zero, centrifugal: zero,
+            _private: () }
+    }
+
+    pub fn total(&self) -> Vector3 {
+        self.drag + self.magnus + self.gravity + self.coriolis + self.centrifugal
+    }
+}
+
+impl TrajectorySample {
+    pub fn new(time: f64, position: Vector3, velocity: Vector3, spin: Vector3,
+        forces: Forces, mach: f64, reynolds: f64) -> TrajectorySample {
+        TrajectorySample{ time, position, velocity, spin,
+            forces, mach, reynolds, _private: () }
+    }
+}
+
+impl TrajectoryResult {
+    pub fn new(samples: Vec<TrajectorySample>, error_estimate: Option<f64>,
+        landing: Option<EventHit>, apex: Option<EventHit>,
+        events: Vec<(usize, EventHit)>) -> TrajectoryResult {
+        TrajectoryResult{ samples, error_estimate,
+            landing, apex, events, _private: () }
+    }
+
+    pub fn positions(&self) -> Vec<Vector3> {
+        self.samples.iter().map(|s| s.position).collect()
+    }
+
+    pub fn first(&self) -> &TrajectorySample {
+        &self.samples[0]
+    }
+
+    // Landing sample, or the last sample if the flight did not land
+    pub fn last(&self) -> &TrajectorySample {
+        &self.samples[self.samples.len() - 1]
+    }
+
+    // Sample with the largest height
+    fn highest(&self) -> &TrajectorySample {
+        self.samples.iter()
+            .fold(self.first(), |top, s| {
+                if s.position.z > top.position.z { s } else { top }
+            })
+    }
+
+
+    /*****************
+    ******************
+    Summary Quantities
+    ******************
+    *****************/
+
+    // Horizontal distance from launch to landing
+    pub fn carry(&self) -> f64 {
+        let delta = self.last().position - self.first().position;
+        (delta.x.powi(2) + delta.y.powi(2)).sqrt()
+    }
+
+    pub fn apex_height(&self) -> f64 {
+        match self.apex {
+            Some(apex) => apex.position.z,
+            None => self.highest().position.z,
+        }
+    }
+
+    pub fn apex_time(&self) -> f64 {
+        match self.apex {
+            Some(apex) => apex.time,
+            None => self.highest().time,
+        }
+    }
+
+    pub fn hang_time(&self) -> f64 {
+        self.last().time - self.first().time
+    }
+
+    // Angle of descent below the horizontal at landing (rad)
+    pub fn landing_angle(&self) -> f64 {
+        let v = self.last().velocity;
+        atan2(-v.z, (v.x.powi(2) + v.y.powi(2)).sqrt())
+    }
+
+    pub fn landing_speed(&self) -> f64 {
+        self.last().velocity.mag()
+    }
+
+    // Horizontal distance of the landing point from the initial
+    // direction of travel (x axis for vertical launches), positive to the left
+    pub fn lateral_offset(&self) -> f64 {
+        let v0 = self.first().velocity;
+        let mut heading = Vector3::new(v0.x, v0.y, 0.);
+        if heading.mag() == 0. {
+            heading = Vector3::new(1., 0., 0.);
+        }
+        let delta = self.last().position - self.first().position;
+        Vector3::cross_prod(&heading.normalize(), &delta).z
+    }
+}
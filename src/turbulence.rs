@@ -0,0 +1,195 @@
+// Stochastic turbulence on top of a mean wind
+//
+// Gust components follow the Dryden or von Karman spectra of
+// MIL-F-8785C with the same intensity and length scale for every
+// component (the medium/high altitude form). Each component is a sum of
+// cosines with random phases and the spectrum's power, so the gusts are
+// smooth, time correlated and reproducible from the seed
+// https://en.wikipedia.org/wiki/Dryden_Wind_Turbulence_Model
+// https://en.wikipedia.org/wiki/Von_K%C3%A1rm%C3%A1n_wind_turbulence_model
+
+use std::sync::Arc;
+use std::f64::consts::PI;
+use libm::cos;
+use log::warn;
+
+use crate::vector3::Vector3;
+use crate::random::Rng;
+use crate::wind::WindField;
+
+// Number of cosines per component and the range of their
+// frequencies, in units of mean_speed / length_scale
+const N_MODES: usize = 128;
+const MIN_FREQUENCY: f64 = 1e-3;
+const MAX_FREQUENCY: f64 = 1e3;
+// Constant in the von Karman spectra
+const KARMAN_SCALE: f64 = 1.339;
+// Smallest mean_speed accepted (m/s), lower values would put every
+// frequency at zero
+const MIN_MEAN_SPEED: f64 = 0.1;
+// Smallest length_scale accepted (m), the frequencies scale with its
+// inverse
+const MIN_LENGTH_SCALE: f64 = 1.;
+
+#[derive(Copy, Clone)]
+pub enum Spectrum {
+    Dryden,
+    VonKarman,
+}
+
+// One cosine of each gust component at a shared frequency (rad/s)
+#[derive(Copy, Clone)]
+struct Mode {
+    frequency: f64,
+    amplitudes: [f64; 3],
+    phases: [f64; 3],
+}
+
+// Mean wind plus turbulence, gust components are longitudinal (along
+// the horizontal mean wind, or x when calm), lateral and vertical
+#[derive(Clone)]
+pub struct Turbulence {
+    pub mean: Arc<dyn WindField>,
+    pub spectrum: Spectrum,
+    // Standard deviation of each gust component (m/s)
+    pub intensity: f64,
+    pub length_scale: f64,
+    // Speed the turbulence is carried past at (m/s), sets the time scale
+    // length_scale / mean_speed. The gusts only depend on time, so for a
+    // projectile this should be its airspeed rather than the wind speed
+    pub mean_speed: f64,
+    modes: Vec<Mode>,
+    _private: (),
+}
+
+impl Spectrum {
+    // One sided power spectral density of the longitudinal and the
+    // lateral/vertical components at angular frequency omega,
+    // each integrates to intensity^2 over [0, infinity)
+    pub fn power(&self, intensity: f64, length_scale: f64, mean_speed: f64,
+        omega: f64) -> (f64, f64) {
+        let scale = intensity.powi(2) * length_scale / (PI * mean_speed);
+        let x = length_scale * omega / mean_speed;
+        match self {
+            Spectrum::Dryden => (
+                2. * scale / (1. + x.powi(2)),
+                scale * (1. + 3. * x.powi(2)) / (1. + x.powi(2)).powi(2)),
+            Spectrum::VonKarman => {
+                let kx2 = (KARMAN_SCALE * x).powi(2);
+                (2. * scale / (1. + kx2).powf(5. / 6.),
+                    scale * (1. + 8. / 3. * kx2) / (1. + kx2).powf(11. / 6.))
+            },
+        }
+    }
+}
+
+impl Turbulence {
+    // mean_speed below MIN_MEAN_SPEED and length_scale below
+    // MIN_LENGTH_SCALE, or not numbers, are set to those minimums
+    pub fn new(mean: Arc<dyn WindField>, spectrum: Spectrum, intensity: f64,
+        mut length_scale: f64, mut mean_speed: f64, seed: u64) -> Turbulence {
+        if length_scale.is_nan() || length_scale < MIN_LENGTH_SCALE {
+            warn!("Input length scale is less than {} m. Setting to {} m.",
+                MIN_LENGTH_SCALE, MIN_LENGTH_SCALE);
+            length_scale = MIN_LENGTH_SCALE;
+        }
+        if mean_speed.is_nan() || mean_speed < MIN_MEAN_SPEED {
+            warn!("Input mean speed is less than {} m/s. Setting to {} m/s.",
+                MIN_MEAN_SPEED, MIN_MEAN_SPEED);
+            mean_speed = MIN_MEAN_SPEED;
+        }
+
+        // Log spaced frequency bins, one cosine at a random
+        // frequency inside each
+        let mut rng = Rng::new(seed);
+        let base = mean_speed / length_scale;
+        let ratio = (MAX_FREQUENCY / MIN_FREQUENCY).powf(1. / N_MODES as f64);
+        let modes = (0..N_MODES).map(|k| {
+            let low = base * MIN_FREQUENCY * ratio.powi(k as i32);
+            let width = low * (ratio - 1.);
+            let frequency = low + width * rng.uniform();
+            let (longitudinal, transverse) = spectrum.power(
+                intensity, length_scale, mean_speed, frequency);
+            let amplitudes = [longitudinal, transverse, transverse]
+                .map(|power| (2. * power * width).sqrt());
+            let phases = [0; 3].map(|_| 2. * PI * rng.uniform());
+            Mode{ frequency, amplitudes, phases }
+        }).collect();
+
+        Turbulence{ mean, spectrum, intensity, length_scale, mean_speed,
+            modes, _private: () }
+    }
+
+    // Longitudinal, lateral and vertical gust components at a time
+    pub fn gust(&self, time: f64) -> [f64; 3] {
+        let mut gust = [0.; 3];
+        for mode in &self.modes {
+            for (i, component) in gust.iter_mut().enumerate() {
+                *component += mode.amplitudes[i]
+                    * cos(mode.frequency * time + mode.phases[i]);
+            }
+        }
+        gust
+    }
+}
+
+impl WindField for Turbulence {
+    fn velocity(&self, position: Vector3, time: f64) -> Vector3 {
+        let mean = self.mean.velocity(position, time);
+        let horizontal = Vector3::new(mean.x, mean.y, 0.);
+        let along = if horizontal.mag() > 0. {
+            horizontal.normalize()
+        } else {
+            Vector3::new(1., 0., 0.)
+        };
+        let across = Vector3::new(-along.y, along.x, 0.);
+        let [u, v, w] = self.gust(time);
+        mean + along * u + across * v + Vector3::new(0., 0., w)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wind::ConstantWind;
+
+    fn turbulence(spectrum: Spectrum, length_scale: f64) -> Turbulence {
+        let mean = Arc::new(ConstantWind::new(Vector3::new(5., 0., 0.)));
+        Turbulence::new(mean, spectrum, 1.5, length_scale, 10., 42)
+    }
+
+    // Each component's variance over a long run is the intensity squared
+    #[test]
+    fn gust_variance_is_the_intensity_squared() {
+        for spectrum in [Spectrum::Dryden, Spectrum::VonKarman] {
+            let turbulence = turbulence(spectrum, 100.);
+            let n_samples = 4_000;
+            let mut sum = [0.; 3];
+            let mut sum_squares = [0.; 3];
+            for k in 0..n_samples {
+                let gust = turbulence.gust(k as f64 * 37.3);
+                for i in 0..3 {
+                    sum[i] += gust[i];
+                    sum_squares[i] += gust[i].powi(2);
+                }
+            }
+            for i in 0..3 {
+                let mean = sum[i] / n_samples as f64;
+                let variance = sum_squares[i] / n_samples as f64 - mean.powi(2);
+                assert!(mean.abs() < 0.1, "mean {}", mean);
+                assert!((variance / 1.5f64.powi(2) - 1.).abs() < 0.05,
+                    "variance {}", variance);
+            }
+        }
+    }
+
+    #[test]
+    fn bad_length_scales_are_clamped() {
+        for length_scale in [0., -10., f64::NAN] {
+            let turbulence = turbulence(Spectrum::Dryden, length_scale);
+            assert_eq!(turbulence.length_scale, MIN_LENGTH_SCALE);
+            assert!(turbulence.gust(3.).iter().all(|g| g.is_finite()));
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::ops::{Add, Sub, Mul, Div};
 
+#[allow(clippy::manual_non_exhaustive)]
 #[derive(Copy, Clone)]
 pub struct Vector3 {
     pub x: f64,
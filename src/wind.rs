@@ -0,0 +1,339 @@
+// Wind that varies with position and time
+//
+// Positions are in trajectory coordinates, with z the height above the
+// atmosphere's reference elevation (usually the ground at launch)
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::f64::consts::PI;
+use libm::{cos, sin};
+use ndarray::Array3;
+
+use crate::vector3::Vector3;
+use crate::parse::{self, ParseError};
+
+pub trait WindField: Send + Sync {
+    // Wind velocity at a point and time since launch
+    fn velocity(&self, position: Vector3, time: f64) -> Vector3;
+}
+
+
+/*************
+**************
+Constant Wind
+**************
+*************/
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct ConstantWind {
+    pub velocity: Vector3,
+    _private: (),
+}
+
+impl ConstantWind {
+    pub fn new(velocity: Vector3) -> ConstantWind {
+        ConstantWind{ velocity, _private: () }
+    }
+}
+
+impl WindField for ConstantWind {
+    fn velocity(&self, _position: Vector3, _time: f64) -> Vector3 {
+        self.velocity
+    }
+}
+
+
+/***************
+****************
+Boundary Layer
+****************
+***************/
+
+// How the wind speed grows with height
+#[derive(Copy, Clone)]
+pub enum ProfileLaw {
+    // u(z) = u_ref * ln(z / z0) / ln(z_ref / z0), z0 the roughness length,
+    // calm at and below z0
+    Logarithmic { roughness_length: f64 },
+    // u(z) = u_ref * (z / z_ref)^exponent, calm at and below the ground
+    PowerLaw { exponent: f64 },
+}
+
+// Wind in a fixed direction whose speed follows a boundary layer profile,
+// reference_velocity is measured at reference_height
+// https://en.wikipedia.org/wiki/Log_wind_profile
+// https://en.wikipedia.org/wiki/Wind_profile_power_law
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Copy, Clone)]
+pub struct WindProfile {
+    pub reference_velocity: Vector3,
+    pub reference_height: f64,
+    pub law: ProfileLaw,
+    _private: (),
+}
+
+impl WindProfile {
+    pub fn new(reference_velocity: Vector3, reference_height: f64,
+        law: ProfileLaw) -> WindProfile {
+        WindProfile{ reference_velocity, reference_height, law, _private: () }
+    }
+
+    // Typical roughness lengths (m) are about 0.0002 over open water,
+    // 0.03 over short grass and 1 in towns
+    pub fn logarithmic(reference_velocity: Vector3, reference_height: f64,
+        roughness_length: f64) -> WindProfile {
+        WindProfile::new(reference_velocity, reference_height,
+            ProfileLaw::Logarithmic{ roughness_length })
+    }
+
+    // An exponent of about 1/7 is typical over open land
+    pub fn power_law(reference_velocity: Vector3, reference_height: f64,
+        exponent: f64) -> WindProfile {
+        WindProfile::new(reference_velocity, reference_height,
+            ProfileLaw::PowerLaw{ exponent })
+    }
+
+    // Wind speed at height z relative to the reference height
+    pub fn factor(&self, z: f64) -> f64 {
+        match self.law {
+            ProfileLaw::Logarithmic { roughness_length } => {
+                if z <= roughness_length {
+                    return 0.;
+                }
+                (z / roughness_length).ln()
+                    / (self.reference_height / roughness_length).ln()
+            },
+            ProfileLaw::PowerLaw { exponent } => {
+                if z <= 0. {
+                    return 0.;
+                }
+                (z / self.reference_height).powf(exponent)
+            },
+        }
+    }
+}
+
+impl WindField for WindProfile {
+    fn velocity(&self, position: Vector3, _time: f64) -> Vector3 {
+        self.reference_velocity * self.factor(position.z)
+    }
+}
+
+
+/***********
+************
+Gridded Wind
+************
+***********/
+
+// Wind measured or simulated on a rectilinear 3D grid,
+// velocities[[i, j, k]] is at (x[i], y[j], z[k])
+// Trilinear interpolation inside the grid, clamped to the edge outside
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Clone)]
+pub struct GriddedWind {
+    pub x: Vec<f64>,
+    pub y: Vec<f64>,
+    pub z: Vec<f64>,
+    pub velocities: Array3<Vector3>,
+    _private: (),
+}
+
+impl GriddedWind {
+    // Axes must have at least 2 strictly increasing values each and match
+    // the shape of velocities, panics otherwise
+    pub fn new(x: Vec<f64>, y: Vec<f64>, z: Vec<f64>,
+        velocities: Array3<Vector3>) -> GriddedWind {
+        for (name, axis) in [("x", &x), ("y", &y), ("z", &z)] {
+            assert!(is_grid_axis(axis),
+                "{} needs at least 2 strictly increasing values", name);
+        }
+        assert!(velocities.dim() == (x.len(), y.len(), z.len()),
+            "velocities do not match the shape of the axes");
+        GriddedWind{ x, y, z, velocities, _private: () }
+    }
+
+    // CSV with one grid point per line: x, y, z, u, v, w
+    // Every combination of the x, y and z values must appear once,
+    // in any order
+    pub fn from_csv<P: AsRef<Path>>(path: P) -> Result<GriddedWind, ParseError> {
+        let text = fs::read_to_string(path)?;
+        GriddedWind::from_csv_str(&text)
+    }
+
+    pub fn from_csv_str(text: &str) -> Result<GriddedWind, ParseError> {
+        let rows = parse::csv_numbers(text)?;
+        let lines: Vec<usize> = parse::data_lines(text).map(|(line, _)| line).collect();
+        let last_line = text.lines().count();
+        if rows.is_empty() || rows[0].len() != 6 {
+            return Err(ParseError::InvalidFormat{
+                line: lines.first().copied().unwrap_or(last_line),
+                message: "expected rows of x, y, z, u, v, w".to_string() });
+        }
+
+        let axis = |column: usize| {
+            let mut values: Vec<f64> = rows.iter().map(|row| row[column]).collect();
+            values.sort_by(|a, b| a.total_cmp(b));
+            values.dedup();
+            values
+        };
+        let (x, y, z) = (axis(0), axis(1), axis(2));
+        for (name, values) in [("x", &x), ("y", &y), ("z", &z)] {
+            if !is_grid_axis(values) {
+                return Err(ParseError::InvalidFormat{ line: last_line, message:
+                    format!("{} needs at least 2 distinct finite values", name) });
+            }
+        }
+
+        let index = |values: &[f64], value: f64| {
+            values.binary_search_by(|v| v.total_cmp(&value)).unwrap()
+        };
+        let shape = (x.len(), y.len(), z.len());
+        // Line each grid point was read from, to find duplicates and gaps
+        let mut filled: Array3<Option<usize>> = Array3::from_elem(shape, None);
+        let mut velocities = Array3::from_elem(shape, Vector3::new(0., 0., 0.));
+        for (row, &line) in rows.iter().zip(&lines) {
+            let cell = [index(&x, row[0]), index(&y, row[1]), index(&z, row[2])];
+            if let Some(first) = filled[cell] {
+                return Err(ParseError::InvalidFormat{ line, message: format!(
+                    "grid point ({}, {}, {}) already given on line {}",
+                    row[0], row[1], row[2], first) });
+            }
+            filled[cell] = Some(line);
+            velocities[cell] = Vector3::new(row[3], row[4], row[5]);
+        }
+        if let Some(((i, j, k), _)) = filled.indexed_iter()
+            .find(|(_, line)| line.is_none()) {
+            return Err(ParseError::InvalidFormat{ line: last_line, message: format!(
+                "no wind for grid point ({}, {}, {})", x[i], y[j], z[k]) });
+        }
+        Ok(GriddedWind::new(x, y, z, velocities))
+    }
+}
+
+// At least 2 finite values, strictly increasing
+fn is_grid_axis(axis: &[f64]) -> bool {
+    axis.len() >= 2 && axis.iter().all(|v| v.is_finite())
+        && axis.windows(2).all(|pair| pair[0] < pair[1])
+}
+
+// Index of the grid interval containing value and the fraction along it
+fn locate(axis: &[f64], value: f64) -> (usize, f64) {
+    if axis.len() < 2 {
+        return (0, 0.);
+    }
+    let value = value.clamp(axis[0], axis[axis.len() - 1]);
+    let i = axis.partition_point(|v| *v <= value).clamp(1, axis.len() - 1) - 1;
+    (i, (value - axis[i]) / (axis[i + 1] - axis[i]))
+}
+
+impl WindField for GriddedWind {
+    fn velocity(&self, position: Vector3, _time: f64) -> Vector3 {
+        let (i, fx) = locate(&self.x, position.x);
+        let (j, fy) = locate(&self.y, position.y);
+        let (k, fz) = locate(&self.z, position.z);
+        let (ni, nj, nk) = self.velocities.dim();
+
+        let mut velocity = Vector3::new(0., 0., 0.);
+        for (di, wx) in [(0, 1. - fx), (1, fx)] {
+            for (dj, wy) in [(0, 1. - fy), (1, fy)] {
+                for (dk, wz) in [(0, 1. - fz), (1, fz)] {
+                    let corner = self.velocities[[(i + di).min(ni - 1),
+                        (j + dj).min(nj - 1), (k + dk).min(nk - 1)]];
+                    velocity = velocity + corner * (wx * wy * wz);
+                }
+            }
+        }
+        velocity
+    }
+}
+
+
+/****
+*****
+Gusts
+*****
+****/
+
+// Time history of a gust, scaling its amplitude
+#[derive(Copy, Clone)]
+pub enum GustShape {
+    // Discrete "1 - cosine" gust, rising from calm to the full amplitude
+    // half way through and back to calm at start + duration
+    OneMinusCosine { start: f64, duration: f64 },
+    // Steady oscillation, amplitude * sin(2 pi t / period + phase)
+    Sinusoidal { period: f64, phase: f64 },
+}
+
+// Mean wind with a deterministic gust on top
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Clone)]
+pub struct GustyWind {
+    pub mean: Arc<dyn WindField>,
+    pub amplitude: Vector3,
+    pub shape: GustShape,
+    _private: (),
+}
+
+impl GustyWind {
+    pub fn new(mean: Arc<dyn WindField>, amplitude: Vector3,
+        shape: GustShape) -> GustyWind {
+        GustyWind{ mean, amplitude, shape, _private: () }
+    }
+
+    pub fn gust_factor(&self, time: f64) -> f64 {
+        match self.shape {
+            GustShape::OneMinusCosine { start, duration } => {
+                if time < start || time > start + duration {
+                    return 0.;
+                }
+                (1. - cos(2. * PI * (time - start) / duration)) / 2.
+            },
+            GustShape::Sinusoidal { period, phase } =>
+                sin(2. * PI * time / period + phase),
+        }
+    }
+}
+
+impl WindField for GustyWind {
+    fn velocity(&self, position: Vector3, time: f64) -> Vector3 {
+        self.mean.velocity(position, time)
+            + self.amplitude * self.gust_factor(time)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_line(text: &str) -> usize {
+        match GriddedWind::from_csv_str(text) {
+            Err(ParseError::InvalidFormat{ line, .. }) => line,
+            _ => panic!("expected a format error"),
+        }
+    }
+
+    #[test]
+    fn gridded_wind_reads_a_full_grid() {
+        let text = "0,0,0,1,0,0\n1,0,0,3,0,0\n0,1,0,1,0,0\n1,1,0,3,0,0\n\
+            0,0,1,1,0,0\n1,0,1,3,0,0\n0,1,1,1,0,0\n1,1,1,3,0,0\n";
+        let wind = GriddedWind::from_csv_str(text).unwrap();
+        let velocity = wind.velocity(Vector3::new(0.5, 0.5, 0.5), 0.);
+        assert!((velocity.x - 2.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn gridded_wind_rejects_duplicates_and_gaps() {
+        // (1, 1, 1) replaced by a second (0, 0, 0)
+        let duplicate = "0,0,0,1,0,0\n1,0,0,3,0,0\n0,1,0,1,0,0\n1,1,0,3,0,0\n\
+            0,0,1,1,0,0\n1,0,1,3,0,0\n0,1,1,1,0,0\n0,0,0,3,0,0\n";
+        assert_eq!(error_line(duplicate), 8);
+        let gap = "0,0,0,1,0,0\n1,0,0,3,0,0\n0,1,0,1,0,0\n1,1,0,3,0,0\n\
+            0,0,1,1,0,0\n1,0,1,3,0,0\n0,1,1,1,0,0\n";
+        assert_eq!(error_line(gap), 7);
+        let flat = "0,0,0,1,0,0\n1,0,0,3,0,0\n";
+        assert_eq!(error_line(flat), 2);
+    }
+}